@@ -0,0 +1,360 @@
+use std::{
+    env,
+    fmt::{Display, Write},
+    io::{self, IsTerminal},
+};
+
+use crate::lexer::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// Source location pointed at by a diagnostic, with an optional message
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Edit that fixes the problem, replacing the text at `span`
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub span: Span,
+    pub message: String,
+    pub replacement: String,
+}
+
+/// How diagnostics are printed, chosen with `--error-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Source snippets for people to read
+    Human,
+    /// One JSON object per line for tools
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "human" => Some(Format::Human),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Problem found in the program, reported with the source code it is about
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the kind of problem, e.g. `E0101`
+    pub code: Option<String>,
+    pub message: String,
+    /// Where the problem is
+    pub primary: Option<Label>,
+    /// Related locations, e.g. a previous definition
+    pub secondary: Vec<Label>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, Some(code), message)
+    }
+
+    pub fn warning(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, Some(code), message)
+    }
+
+    /// Error with no code, for failures outside of the program itself
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, None, message)
+    }
+
+    fn new(severity: Severity, code: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: code.map(str::to_owned),
+            message: message.into(),
+            primary: None,
+            secondary: vec![],
+            help: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    pub fn with_primary(mut self, span: &Span, message: impl Into<String>) -> Self {
+        self.primary = Some(Label {
+            span: span.clone(),
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: &Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span: span.clone(),
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        span: &Span,
+        message: impl Into<String>,
+        replacement: impl Into<String>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span: span.clone(),
+            message: message.into(),
+            replacement: replacement.into(),
+        });
+        self
+    }
+
+    /// Line of the primary span, 0 when there is none
+    pub fn line(&self) -> usize {
+        self.primary.as_ref().map_or(0, |label| label.span.line)
+    }
+
+    /// Renders the diagnostic the way rustc does, with the source lines it
+    /// points at taken from `source`
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let style = Style { color };
+        let severity_style = match self.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        let mut out = String::new();
+        let header = match &self.code {
+            Some(code) => format!("{}[{code}]", self.severity),
+            None => self.severity.to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            style.paint(&header, severity_style),
+            style.paint(&format!(": {}", self.message), BOLD)
+        );
+
+        let labels: Vec<(&Label, bool)> = self
+            .primary
+            .iter()
+            .map(|label| (label, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+            .collect();
+        let Some((first, _)) = labels.first() else {
+            for help in self.help_lines() {
+                let _ = writeln!(out, "{} {help}", style.paint("help:", BOLD));
+            }
+            return out;
+        };
+
+        let lines: Vec<&str> = source.lines().collect();
+        let width = labels
+            .iter()
+            .map(|(label, _)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = style.paint(&format!("{:width$} |", ""), BLUE);
+
+        let _ = writeln!(
+            out,
+            "{}{} {}",
+            " ".repeat(width),
+            style.paint("-->", BLUE),
+            first.span
+        );
+        let _ = writeln!(out, "{gutter}");
+
+        let mut sorted = labels.clone();
+        sorted.sort_by_key(|(label, _)| (label.span.line, label.span.column));
+        let mut previous_line = None;
+        for (label, primary) in sorted {
+            let line = label.span.line;
+            let Some(text) = lines.get(line.wrapping_sub(1)) else {
+                continue;
+            };
+            if previous_line != Some(line) {
+                let number = style.paint(&format!("{line:>width$} |"), BLUE);
+                let _ = writeln!(out, "{number} {text}");
+            }
+            previous_line = Some(line);
+
+            // Keep tabs so the marks line up with the source
+            let indent: String = text
+                .chars()
+                .take(label.span.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let rest = text.chars().count().saturating_sub(label.span.column - 1);
+            let length = source
+                .get(label.span.start..label.span.end)
+                .map_or(1, |s| s.lines().next().unwrap_or("").chars().count())
+                .clamp(1, rest.max(1));
+            let (mark, mark_style) = match primary {
+                true => ("^", severity_style),
+                false => ("-", BLUE),
+            };
+            let marks = format!("{} {}", mark.repeat(length), label.message);
+            let _ = writeln!(
+                out,
+                "{gutter} {indent}{}",
+                style.paint(marks.trim_end(), mark_style)
+            );
+        }
+
+        let help_lines = self.help_lines();
+        if !help_lines.is_empty() {
+            let _ = writeln!(out, "{gutter}");
+        }
+        for help in help_lines {
+            let equal = style.paint(&format!("{:width$} =", ""), BLUE);
+            let _ = writeln!(out, "{equal} {}: {help}", style.paint("help", BOLD));
+        }
+        out
+    }
+
+    fn help_lines(&self) -> Vec<String> {
+        let suggestions = self
+            .suggestions
+            .iter()
+            .map(|suggestion| format!("{}: `{}`", suggestion.message, suggestion.replacement));
+        self.help.iter().cloned().chain(suggestions).collect()
+    }
+
+    /// Serializes the diagnostic as a single line JSON object, `source` is
+    /// used to find where the spans end
+    pub fn to_json(&self, source: &str) -> String {
+        let labels = self
+            .primary
+            .iter()
+            .map(|label| (label, true))
+            .chain(self.secondary.iter().map(|label| (label, false)));
+        let spans: Vec<String> = labels
+            .map(|(label, primary)| {
+                format!(
+                    "{{{},\"is_primary\":{primary},\"label\":{}}}",
+                    span_fields(&label.span, source),
+                    json_string(&label.message)
+                )
+            })
+            .collect();
+        let help: Vec<String> = self.help.iter().map(|help| json_string(help)).collect();
+        let suggestions: Vec<String> = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                format!(
+                    "{{\"message\":{},\"replacement\":{},\"span\":{{{}}}}}",
+                    json_string(&suggestion.message),
+                    json_string(&suggestion.replacement),
+                    span_fields(&suggestion.span, source)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"spans\":[{}],\"help\":[{}],\"suggestions\":[{}]}}",
+            self.severity,
+            self.code.as_deref().map_or("null".to_owned(), json_string),
+            json_string(&self.message),
+            spans.join(","),
+            help.join(","),
+            suggestions.join(",")
+        )
+    }
+}
+
+/// Prints the diagnostics to stderr, in color when it is a terminal
+pub fn emit(diagnostics: &[Diagnostic], source: &str, format: Format) {
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    for diagnostic in diagnostics {
+        match format {
+            Format::Human => eprintln!("{}", diagnostic.render(source, color)),
+            Format::Json => eprintln!("{}", diagnostic.to_json(source)),
+        }
+    }
+}
+
+fn span_fields(span: &Span, source: &str) -> String {
+    // Spans only know where they start, the end is found in the source
+    let (line_end, column_end) =
+        source
+            .get(span.start..span.end)
+            .map_or((span.line, span.column), |text| {
+                let lines = text.matches('\n').count();
+                let last = text.rsplit('\n').next().unwrap_or("").chars().count();
+                match lines {
+                    0 => (span.line, span.column + last),
+                    _ => (span.line + lines, last + 1),
+                }
+            });
+    format!(
+        "\"file\":{},\"line_start\":{},\"column_start\":{},\"line_end\":{line_end},\"column_end\":{column_end},\"byte_start\":{},\"byte_end\":{}",
+        json_string(&span.file),
+        span.line,
+        span.column,
+        span.start,
+        span.end
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, text: &str, style: &str) -> String {
+        match self.color {
+            true => format!("{style}{text}{RESET}"),
+            false => text.to_owned(),
+        }
+    }
+}
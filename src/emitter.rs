@@ -13,28 +13,50 @@ use exe::build;
 use exe::sections::IMAGE_BASE;
 
 pub mod ast;
+mod const_eval;
 #[cfg(target_os = "linux")]
 pub mod elf;
 #[cfg(target_os = "windows")]
 pub mod exe;
+pub mod lints;
+pub mod resolver;
 mod stack;
 mod symbols;
 mod text;
+pub mod type_checker;
 mod variables;
 
-pub fn build_executable(ast: &ast::Block, output_path: PathBuf) {
-    let (variables, scopes) = variables::build_variables(ast);
-    dbg!(&variables);
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Emit {
+    /// Executable starting at `main`
+    #[default]
+    Exe,
+    /// Object file without an entry point, to be linked with other code
+    Obj,
+}
+
+pub fn build_executable(
+    ast: &ast::Block,
+    bindings: &resolver::Bindings,
+    types: &type_checker::Types,
+    output_path: PathBuf,
+    emit: Emit,
+    checked: bool,
+) {
+    let (variables, functions) = variables::build_variables(ast, bindings, types);
 
-    let code_context = text::build_code_context(ast, &variables, &scopes, IMAGE_BASE);
+    let code_context = text::build_code_context(
+        ast, &variables, &functions, bindings, types, IMAGE_BASE, emit, checked,
+    );
 
     let symbol_resolver = SymbolResolver::new();
-    let symbols = symbol_resolver.resolve(&variables, &code_context.get_labels());
+    let symbols = symbol_resolver.resolve(&variables, &functions, &code_context.get_labels());
 
     build(
         output_path,
         &code_context,
         symbols.as_slice(),
         code_context.get_relocations(),
+        emit,
     );
 }
@@ -1,4 +1,6 @@
-use std::{fmt::Display, hash::Hash, mem};
+use std::{cmp::Ordering, fmt::Display, hash::Hash, mem};
+
+pub use crate::lexer::token::Span;
 
 #[derive(Debug, Clone)]
 pub struct Program {
@@ -11,7 +13,7 @@ pub enum Item {
     VarDeclaration,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Type {
     pub name: TypeName,
     pub modifiers: Vec<TypeModifer>,
@@ -28,11 +30,11 @@ impl Display for Type {
             s += &format!("{m}");
             s
         });
-        f.write_fmt(format_args!("{} {}", modifiers, self.name))
+        f.write_fmt(format_args!("{}{}", modifiers, self.name))
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeModifer {
     Ref,
 }
@@ -45,10 +47,74 @@ impl Display for TypeModifer {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntType {
+    pub fn size(&self) -> usize {
+        match self {
+            IntType::I8 | IntType::U8 => 1,
+            IntType::I16 | IntType::U16 => 2,
+            IntType::I32 | IntType::U32 => 4,
+            IntType::I64 | IntType::U64 => 8,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64
+        )
+    }
+
+    pub fn fits(&self, value: i128) -> bool {
+        let bits = self.size() as u32 * 8;
+        if self.is_signed() {
+            (-(1_i128 << (bits - 1))..(1_i128 << (bits - 1))).contains(&value)
+        } else {
+            (0..(1_i128 << bits)).contains(&value)
+        }
+    }
+
+    /// The value of `bits` read as this type
+    pub fn value(&self, bits: i64) -> i128 {
+        if self.is_signed() {
+            bits.into()
+        } else {
+            (bits as u64).into()
+        }
+    }
+}
+
+impl Display for IntType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntType::I8 => f.write_str("i8"),
+            IntType::I16 => f.write_str("i16"),
+            IntType::I32 => f.write_str("i32"),
+            IntType::I64 => f.write_str("i64"),
+            IntType::U8 => f.write_str("u8"),
+            IntType::U16 => f.write_str("u16"),
+            IntType::U32 => f.write_str("u32"),
+            IntType::U64 => f.write_str("u64"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeName {
     String,
-    Int,
+    Int(IntType),
+    Char,
     Float,
     Bool,
     Unit,
@@ -58,21 +124,34 @@ impl Display for TypeName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TypeName::String => f.write_str("String"),
-            TypeName::Int => f.write_str("int"),
+            TypeName::Int(IntType::I64) => f.write_str("int"),
+            TypeName::Int(int_type) => write!(f, "{int_type}"),
+            TypeName::Char => f.write_str("char"),
             TypeName::Float => f.write_str("float"),
             TypeName::Bool => f.write_str("bool"),
-            TypeName::Unit => f.write_str(""),
+            TypeName::Unit => f.write_str("()"),
         }
     }
 }
 
-impl From<&str> for TypeName {
-    fn from(value: &str) -> Self {
-        match value {
+impl TryFrom<&str> for TypeName {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let type_name = match value {
             "String" => TypeName::String,
-            "int" => TypeName::Int,
-            _ => panic!("invalid type: {}", value),
-        }
+            "char" => TypeName::Char,
+            "int" | "i64" => TypeName::Int(IntType::I64),
+            "i8" => TypeName::Int(IntType::I8),
+            "i16" => TypeName::Int(IntType::I16),
+            "i32" => TypeName::Int(IntType::I32),
+            "u8" => TypeName::Int(IntType::U8),
+            "u16" => TypeName::Int(IntType::U16),
+            "u32" => TypeName::Int(IntType::U32),
+            "u64" => TypeName::Int(IntType::U64),
+            _ => return Err(format!("Unknown type {value}")),
+        };
+        Ok(type_name)
     }
 }
 
@@ -94,14 +173,35 @@ pub struct FuncDeclaration {
     pub args: Vec<Arg>,
     pub return_type: Type,
     pub body: Block,
+    /// Visible to the linker under its own name
+    pub export: bool,
 }
 impl FuncDeclaration {
-    pub fn new(name: Ident, args: Vec<Arg>, return_type: Type, body: Block) -> Self {
+    pub fn new(name: Ident, args: Vec<Arg>, return_type: Type, body: Block, export: bool) -> Self {
         Self {
             name,
             args,
             return_type,
             body,
+            export,
+        }
+    }
+}
+
+/// Function defined outside of jc (e.g. in libc), called with the C calling
+/// convention
+#[derive(Debug, Clone)]
+pub struct ExternFuncDeclaration {
+    pub name: Ident,
+    pub args: Vec<Arg>,
+    pub return_type: Type,
+}
+impl ExternFuncDeclaration {
+    pub fn new(name: Ident, args: Vec<Arg>, return_type: Type) -> Self {
+        Self {
+            name,
+            args,
+            return_type,
         }
     }
 }
@@ -110,28 +210,56 @@ impl FuncDeclaration {
 pub struct Block {
     pub scope: String,
     pub stmts: Vec<Statement>,
+    /// Location of each statement
+    pub spans: Vec<Span>,
 }
 
 impl Block {
-    pub fn new(scope: String, stmts: Vec<Statement>) -> Self {
-        Self { scope, stmts }
+    pub fn new(scope: String, stmts: Vec<Statement>, spans: Vec<Span>) -> Self {
+        Self {
+            scope,
+            stmts,
+            spans,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum ControlFlow {
     Return(Option<Expression>),
+    If(If),
+}
+
+#[derive(Debug, Clone)]
+pub struct If {
+    pub condition: Expression,
+    pub then: Block,
+    pub otherwise: Option<Block>,
+}
+
+impl If {
+    pub fn new(condition: Expression, then: Block, otherwise: Option<Block>) -> Self {
+        Self {
+            condition,
+            then,
+            otherwise,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
     VarDeclaration(VarDeclaration),
     FuncDeclaration(FuncDeclaration),
+    ExternFuncDeclaration(ExternFuncDeclaration),
     Loop(Loop),
     Assignment(Assignment),
     Expression(Expression),
     ControlFlow(ControlFlow),
     Block(Block),
+    /// Statement that failed to parse, only found in programs with syntax
+    /// errors
+    Error,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -162,19 +290,40 @@ impl TryFrom<&str> for VarDeclarationType {
     }
 }
 
+/// Whether a variable can be assigned after its declaration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutability {
+    Mutable,
+    Immutable,
+}
+
 #[derive(Debug, Clone)]
 
 pub struct VarDeclaration {
     pub name: Ident,
+    pub _type: Option<Type>,
     pub rhs: RhsExpression,
     pub declarion_type: VarDeclarationType,
+    /// `let` variables can be assigned, consts cannot
+    pub mutability: Mutability,
 }
 impl VarDeclaration {
-    pub fn new(id: Ident, expr: RhsExpression, decl_type: VarDeclarationType) -> Self {
+    pub fn new(
+        id: Ident,
+        _type: Option<Type>,
+        expr: RhsExpression,
+        decl_type: VarDeclarationType,
+    ) -> Self {
+        let mutability = match decl_type {
+            VarDeclarationType::Let => Mutability::Mutable,
+            VarDeclarationType::Const => Mutability::Immutable,
+        };
         Self {
             name: id,
+            _type,
             rhs: expr,
             declarion_type: decl_type,
+            mutability,
         }
     }
 }
@@ -199,11 +348,28 @@ impl Assignment {
 
 #[derive(Debug, Clone)]
 pub enum Expression {
-    Unary(UnaryOperation),
+    /// The span includes the operator
+    Unary(UnaryOperation, Span),
     Binary(BinaryOperation),
     Ident(Ident),
-    Literal(Literal),
+    Literal(Literal, Span),
     Call(Call),
+    Index(Index),
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Unary(_, span) | Expression::Literal(_, span) => span.clone(),
+            Expression::Binary(binary) => {
+                let (lhs, rhs) = binary.operands();
+                lhs.span().to(&rhs.span())
+            }
+            Expression::Ident(ident) => ident.span.clone(),
+            Expression::Call(call) => call.span.clone(),
+            Expression::Index(index) => index.span.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -217,16 +383,96 @@ pub enum UnaryOperation {
 pub enum BinaryOperation {
     Plus(Box<Expression>, Box<Expression>),
     Minus(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Rem(Box<Expression>, Box<Expression>),
+    Less(Box<Expression>, Box<Expression>),
+    LessEqual(Box<Expression>, Box<Expression>),
+    Greater(Box<Expression>, Box<Expression>),
+    GreaterEqual(Box<Expression>, Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+}
+
+impl BinaryOperation {
+    pub fn operands(&self) -> (&Expression, &Expression) {
+        match self {
+            BinaryOperation::Plus(lhs, rhs)
+            | BinaryOperation::Minus(lhs, rhs)
+            | BinaryOperation::Mul(lhs, rhs)
+            | BinaryOperation::Div(lhs, rhs)
+            | BinaryOperation::Rem(lhs, rhs)
+            | BinaryOperation::Less(lhs, rhs)
+            | BinaryOperation::LessEqual(lhs, rhs)
+            | BinaryOperation::Greater(lhs, rhs)
+            | BinaryOperation::GreaterEqual(lhs, rhs)
+            | BinaryOperation::Equal(lhs, rhs)
+            | BinaryOperation::NotEqual(lhs, rhs) => (lhs, rhs),
+        }
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        !matches!(
+            self,
+            BinaryOperation::Plus(..)
+                | BinaryOperation::Minus(..)
+                | BinaryOperation::Mul(..)
+                | BinaryOperation::Div(..)
+                | BinaryOperation::Rem(..)
+        )
+    }
+}
+
+impl Display for BinaryOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryOperation::Plus(..) => f.write_str("+"),
+            BinaryOperation::Minus(..) => f.write_str("-"),
+            BinaryOperation::Mul(..) => f.write_str("*"),
+            BinaryOperation::Div(..) => f.write_str("/"),
+            BinaryOperation::Rem(..) => f.write_str("%"),
+            BinaryOperation::Less(..) => f.write_str("<"),
+            BinaryOperation::LessEqual(..) => f.write_str("<="),
+            BinaryOperation::Greater(..) => f.write_str(">"),
+            BinaryOperation::GreaterEqual(..) => f.write_str(">="),
+            BinaryOperation::Equal(..) => f.write_str("=="),
+            BinaryOperation::NotEqual(..) => f.write_str("!="),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Call {
     pub func_name: Ident,
     pub args: Vec<Expression>,
+    /// From the function name to the closing parenthesis
+    pub span: Span,
 }
 impl Call {
-    pub(crate) fn new(func_name: Ident, args: Vec<Expression>) -> Self {
-        Self { func_name, args }
+    pub(crate) fn new(func_name: Ident, args: Vec<Expression>, span: Span) -> Self {
+        Self {
+            func_name,
+            args,
+            span,
+        }
+    }
+}
+
+/// Byte of a string: `value[index]`
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub value: Box<Expression>,
+    pub index: Box<Expression>,
+    /// From the start of the value to the closing bracket
+    pub span: Span,
+}
+impl Index {
+    pub fn new(value: Expression, index: Expression, span: Span) -> Self {
+        Self {
+            value: Box::new(value),
+            index: Box::new(index),
+            span,
+        }
     }
 }
 
@@ -234,6 +480,7 @@ impl Call {
 pub enum Literal {
     String(String),
     Integer(Integer),
+    Char(char),
     // Bool(Bool),
 }
 
@@ -242,24 +489,60 @@ impl Literal {
         match self {
             Literal::String(s) => s.len(),
             Literal::Integer(n) => mem::size_of_val(&n.value),
+            Literal::Char(_) => mem::size_of::<u32>(),
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, PartialOrd, Ord)]
+/// Identifiers compare by name, wherever they are in the source
+#[derive(Debug, Clone)]
 pub struct Ident {
     pub value: String,
+    pub span: Span,
+}
+
+impl PartialEq for Ident {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Ident {}
+
+impl Hash for Ident {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl PartialOrd for Ident {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ident {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Integer {
-    pub value: i64,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum LoopIter {
+    /// Integers from start up to end, both known at compile time
+    Range { start: Expression, end: Expression },
+    /// Chars of a string
+    Collection(Expression),
 }
 
 #[derive(Debug, Clone)]
 pub struct Loop {
     pub var: Ident,
-    pub start: u64,
-    pub end: u64,
+    pub iter: LoopIter,
     pub body: Block,
 }
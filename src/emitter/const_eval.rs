@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use super::{
+    ast,
+    resolver::Bindings,
+    variables::{Value, ValueLocation, Variable},
+};
+use crate::diagnostics::Diagnostic;
+
+/// Value of a const, as bits, and its type
+type Lookup<'a> = dyn Fn(&ast::Ident) -> Option<(i64, ast::IntType)> + 'a;
+
+/// Evaluates the integer expression `expr` as `int_type`, `None` when it
+/// depends on values only known at runtime. Literals and int consts are
+/// known, overflow and division by zero are reported at the expression.
+pub fn eval_int(
+    variables: &BTreeMap<String, Variable>,
+    bindings: &Bindings,
+    expr: &ast::Expression,
+    int_type: ast::IntType,
+) -> Result<Option<i64>, Box<Diagnostic>> {
+    let lookup = |id: &ast::Ident| match variables.get(&bindings.get(id).path)? {
+        Variable {
+            value_loc: ValueLocation::DataSection(_),
+            value_type: Value::Int(int_type, n),
+            ..
+        } => Some((*n, *int_type)),
+        _ => None,
+    };
+    eval_int_with(&lookup, expr, int_type)
+}
+
+/// Same as [`eval_int`], with the value of identifiers and their type given
+/// by `lookup`
+pub fn eval_int_with(
+    lookup: &Lookup<'_>,
+    expr: &ast::Expression,
+    int_type: ast::IntType,
+) -> Result<Option<i64>, Box<Diagnostic>> {
+    // The bits of the value, u64 above i64::MAX wraps to negative
+    Ok(eval(lookup, expr, int_type)?.map(|value| value as i64))
+}
+
+/// Evaluates in i128, wide enough for both i64 and u64 and their overflows
+fn eval(
+    lookup: &Lookup<'_>,
+    expr: &ast::Expression,
+    int_type: ast::IntType,
+) -> Result<Option<i128>, Box<Diagnostic>> {
+    let error = |message: String, label: String| {
+        Err(Box::new(
+            Diagnostic::error("E0207", message).with_primary(&expr.span(), label),
+        ))
+    };
+    let overflow = format!("overflows {int_type}");
+
+    let value = match expr {
+        ast::Expression::Literal(ast::Literal::Integer(n), _) => n.value.into(),
+        ast::Expression::Ident(id) => match lookup(id) {
+            Some((n, id_type)) => id_type.value(n),
+            None => return Ok(None),
+        },
+        ast::Expression::Unary(ast::UnaryOperation::Minus(inner), _) => {
+            // A negated literal must fit, not the literal itself
+            let n = match inner.as_ref() {
+                ast::Expression::Literal(ast::Literal::Integer(n), _) => n.value.into(),
+                inner => match eval(lookup, inner, int_type)? {
+                    Some(n) => n,
+                    None => return Ok(None),
+                },
+            };
+            match n.checked_neg() {
+                Some(value) => value,
+                None => {
+                    return error(
+                        format!("overflow evaluating -({n}) as {int_type}"),
+                        overflow,
+                    )
+                }
+            }
+        }
+        ast::Expression::Binary(binary) if binary.is_comparison() => {
+            let (lhs, rhs) = binary.operands();
+            let operand_type = operand_type(lookup, lhs)
+                .or_else(|| operand_type(lookup, rhs))
+                .unwrap_or(ast::IntType::I64);
+            // u64 values are positive in i128, they compare unsigned
+            let lhs = eval(lookup, lhs, operand_type)?;
+            let rhs = eval(lookup, rhs, operand_type)?;
+            let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+                return Ok(None);
+            };
+            let holds = match binary {
+                ast::BinaryOperation::Equal(..) => lhs == rhs,
+                ast::BinaryOperation::NotEqual(..) => lhs != rhs,
+                ast::BinaryOperation::Less(..) => lhs < rhs,
+                ast::BinaryOperation::LessEqual(..) => lhs <= rhs,
+                ast::BinaryOperation::Greater(..) => lhs > rhs,
+                _ => lhs >= rhs,
+            };
+            holds as i128
+        }
+        ast::Expression::Binary(binary) => {
+            // Both sides are evaluated so that errors in either are found
+            let (lhs, rhs) = binary.operands();
+            let lhs = eval(lookup, lhs, int_type)?;
+            let rhs = eval(lookup, rhs, int_type)?;
+            let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+                return Ok(None);
+            };
+            let value = match binary {
+                ast::BinaryOperation::Plus(..) => lhs.checked_add(rhs),
+                ast::BinaryOperation::Minus(..) => lhs.checked_sub(rhs),
+                ast::BinaryOperation::Mul(..) => lhs.checked_mul(rhs),
+                ast::BinaryOperation::Div(..) | ast::BinaryOperation::Rem(..) if rhs == 0 => {
+                    return error(
+                        format!("division by zero evaluating {lhs} {binary} {rhs}"),
+                        "division by zero".to_owned(),
+                    );
+                }
+                ast::BinaryOperation::Div(..) => lhs.checked_div(rhs),
+                _ => lhs.checked_rem(rhs),
+            };
+            match value {
+                Some(value) if int_type.fits(value) => value,
+                _ => {
+                    return error(
+                        format!("overflow evaluating {lhs} {binary} {rhs} as {int_type}"),
+                        overflow,
+                    )
+                }
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    if !int_type.fits(value) {
+        return error(
+            format!("constant {value} does not fit in {int_type}"),
+            format!("out of range for {int_type}"),
+        );
+    }
+    Ok(Some(value))
+}
+
+/// Type of the consts `expr` is computed from, literals take the type of
+/// the other operand
+fn operand_type(lookup: &Lookup<'_>, expr: &ast::Expression) -> Option<ast::IntType> {
+    match expr {
+        ast::Expression::Ident(id) => lookup(id).map(|(_, int_type)| int_type),
+        ast::Expression::Unary(ast::UnaryOperation::Minus(inner), _) => operand_type(lookup, inner),
+        ast::Expression::Binary(binary) if !binary.is_comparison() => {
+            let (lhs, rhs) = binary.operands();
+            operand_type(lookup, lhs).or_else(|| operand_type(lookup, rhs))
+        }
+        _ => None,
+    }
+}
@@ -19,6 +19,7 @@ use self::sections::*;
 use super::{
     symbols::{self},
     text::{CodeContext, Sliceable},
+    Emit,
 };
 
 pub fn build(
@@ -26,8 +27,12 @@ pub fn build(
     code_context: &CodeContext,
     symbols: &[symbols::Symbol],
     relocations: &[symbols::Relocation],
+    emit: Emit,
 ) {
-    let object_file = output_path.with_extension("o");
+    let object_file = match emit {
+        Emit::Exe => output_path.with_extension("o"),
+        Emit::Obj => output_path.clone(),
+    };
 
     let data_section_data = build_data_section(symbols);
 
@@ -45,7 +50,7 @@ pub fn build(
     ];
     let shstrtab_section_data: Vec<u8> = build_shstrtab_section(section_names);
     let (symbols, relocations, last_local_idx) =
-        build_symbols(object_file.to_str().unwrap(), relocations, &symbols);
+        build_symbols(object_file.to_str().unwrap(), relocations, &symbols, emit);
     let symstr = SymStr::new(symbols.as_slice());
     let symtab_section_data = symstr.get_symtab();
     let strtab_section_data = symstr.get_strtab();
@@ -126,6 +131,10 @@ pub fn build(
     file.write_all(&strtab_section_data).unwrap();
     file.write_all(&relocation_section_data).unwrap();
 
+    if emit == Emit::Obj {
+        return;
+    }
+
     let child = Command::new("gcc")
         .args(&[
             "-nostartfiles",
@@ -146,6 +155,7 @@ fn build_symbols(
     filename: &str,
     relocations: &[symbols::Relocation],
     symbols: &[symbols::Symbol],
+    emit: Emit,
 ) -> (Vec<Symbol>, Vec<Relocation>, usize) {
     let file = Symbol {
         name: filename.to_string(),
@@ -171,18 +181,22 @@ fn build_symbols(
     };
 
     let mut result = vec![file, text, data];
-    let last_idx = result.len();
+
+    // Local symbols have to precede the global ones
+    let (locals, globals): (Vec<_>, Vec<_>) = symbols
+        .iter()
+        .partition(|symbol| matches!(symbol.get_scope(), symbols::SymbolScope::Local));
 
     let mut relocations_result = vec![];
     let mut symbol_idxs = HashMap::new();
-    for (idx, symbol) in symbols.iter().enumerate() {
+    for symbol in locals.into_iter().chain(globals) {
         let _type = match symbol.get_type() {
             symbols::SymbolType::Data(DataSymbol::Comptime) => defs::STT_OBJECT,
             symbols::SymbolType::Text => defs::STT_FUNC,
             _ => continue,
         };
 
-        symbol_idxs.insert(symbol.get_name(), last_idx + idx + 1);
+        symbol_idxs.insert(symbol.get_name(), result.len() + 1);
 
         let section = match symbol.get_section() {
             symbols::Section::Undefined => SHN_UNDEF,
@@ -206,8 +220,10 @@ fn build_symbols(
         result.push(symbol);
     }
 
-    let last_local_idx = result.len();
-    dbg!(last_local_idx);
+    let last_local_idx = result
+        .iter()
+        .take_while(|symbol| symbol.bind == defs::STB_LOCAL)
+        .count();
 
     for rel in relocations {
         let idx = match symbol_idxs.get(rel.get_symbol()) {
@@ -241,16 +257,18 @@ fn build_symbols(
         relocations_result.push(Relocation::new(idx, rel.get_offset() as u64, _type));
     }
 
-    let start = Symbol {
-        name: "_start".to_string(),
-        offset: 0,
-        section: 1,
-        _type: defs::STT_NOTYPE,
-        bind: defs::STB_GLOBAL,
-        ..Default::default()
-    };
+    if emit == Emit::Exe {
+        let start = Symbol {
+            name: "_start".to_string(),
+            offset: 0,
+            section: 1,
+            _type: defs::STT_NOTYPE,
+            bind: defs::STB_GLOBAL,
+            ..Default::default()
+        };
 
-    result.push(start);
+        result.push(start);
+    }
 
     (result, relocations_result, last_local_idx)
 }
@@ -9,14 +9,18 @@ use std::path::PathBuf;
 use std::process::Command;
 use std::{collections::BTreeMap, env, fs, io::Write, mem, time::SystemTime};
 
-use super::{ast, symbols};
+use super::{ast, symbols, Emit};
 
 pub fn build(
     output_path: PathBuf,
     code_context: &CodeContext,
     symbols: &[symbols::Symbol],
     relocations: &[symbols::Relocation],
+    emit: Emit,
 ) {
+    if emit == Emit::Obj {
+        unreachable!("main refuses --emit obj on Windows");
+    }
     let object_file = output_path.with_extension("obj");
 
     let mut debug_code_file =
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    ast, const_eval,
+    resolver::{Bindings, SymbolId, SymbolKind},
+    type_checker,
+};
+use crate::diagnostics::Diagnostic;
+
+/// Kind of warning, each one can be turned off with `--allow <name>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// Variable, const or parameter that is never read
+    UnusedVariable,
+    /// Function that cannot be reached from `main` or an exported function
+    UnusedFunction,
+    /// Statement following a `return` in the same block
+    UnreachableCode,
+    /// Range loop whose bounds are known to be empty
+    EmptyRange,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 4] = [
+        Lint::UnusedVariable,
+        Lint::UnusedFunction,
+        Lint::UnreachableCode,
+        Lint::EmptyRange,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedFunction => "unused-function",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::EmptyRange => "empty-range",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+    fn warning(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::warning(self.name(), message)
+    }
+}
+
+/// Runs every lint not in `allowed` over a resolved and type checked
+/// program, the warnings use the lint name as their code. Names starting
+/// with an underscore are never reported as unused.
+pub fn check(block: &ast::Block, bindings: &Bindings, allowed: &[Lint]) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        bindings,
+        allowed,
+        warnings: vec![],
+        reads: HashSet::new(),
+        calls: HashMap::new(),
+        consts: HashMap::new(),
+        function: None,
+    };
+    linter.visit_block(block);
+    linter.unused_variables();
+    linter.unused_functions(block);
+
+    let mut warnings = linter.warnings;
+    warnings.sort_by_key(Diagnostic::line);
+    warnings
+}
+
+struct Linter<'a> {
+    bindings: &'a Bindings,
+    allowed: &'a [Lint],
+    warnings: Vec<Diagnostic>,
+    // Symbols read at least once
+    reads: HashSet<SymbolId>,
+    // Functions called by each function
+    calls: HashMap<SymbolId, HashSet<SymbolId>>,
+    // Value of every int const declaration
+    consts: HashMap<SymbolId, (&'a ast::Expression, ast::IntType)>,
+    // Function being visited, `None` at the top level
+    function: Option<SymbolId>,
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, warning: Diagnostic) {
+        let lint = warning.code.as_deref().and_then(Lint::from_name);
+        if !lint.is_some_and(|lint| self.allowed.contains(&lint)) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn visit_block(&mut self, block: &'a ast::Block) {
+        let mut returned = None;
+        for (stmt, span) in block.stmts.iter().zip(&block.spans) {
+            if let Some(return_span) = returned.take() {
+                // Only the first unreachable statement of a block is reported
+                let warning = Lint::UnreachableCode
+                    .warning("unreachable statement after return")
+                    .with_primary(span, "unreachable statement")
+                    .with_secondary(return_span, "any code following this return is unreachable");
+                self.warn(warning);
+            } else if let ast::Statement::ControlFlow(ast::ControlFlow::Return(_)) = stmt {
+                returned = Some(span);
+            }
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &'a ast::Statement) {
+        match statement {
+            ast::Statement::VarDeclaration(var_decl) => {
+                if let ast::RhsExpression::Expression(expr) = &var_decl.rhs {
+                    if var_decl.declarion_type == ast::VarDeclarationType::Const {
+                        let int_type = match &var_decl._type {
+                            Some(ast::Type {
+                                name: ast::TypeName::Int(int_type),
+                                ..
+                            }) => *int_type,
+                            _ => ast::IntType::I64,
+                        };
+                        self.consts
+                            .insert(self.bindings.id(&var_decl.name), (expr, int_type));
+                    }
+                    self.visit_expression(expr);
+                }
+            }
+            ast::Statement::FuncDeclaration(func_decl) => {
+                let id = self.bindings.id(&func_decl.name);
+                self.calls.entry(id).or_default();
+                self.function = Some(id);
+                self.visit_block(&func_decl.body);
+                self.function = None;
+            }
+            ast::Statement::ExternFuncDeclaration(_) => (),
+            ast::Statement::Loop(l) => {
+                match &l.iter {
+                    ast::LoopIter::Range { start, end } => {
+                        self.visit_expression(start);
+                        self.visit_expression(end);
+                        self.empty_range(start, end);
+                    }
+                    ast::LoopIter::Collection(collection) => self.visit_expression(collection),
+                }
+                self.visit_block(&l.body);
+            }
+            ast::Statement::Assignment(assign) => {
+                if let ast::RhsExpression::Expression(expr) = &assign.rhs {
+                    self.visit_expression(expr);
+                }
+            }
+            ast::Statement::Expression(expr) => self.visit_expression(expr),
+            ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => {
+                self.visit_expression(&_if.condition);
+                self.visit_block(&_if.then);
+                if let Some(otherwise) = &_if.otherwise {
+                    self.visit_block(otherwise);
+                }
+            }
+            ast::Statement::ControlFlow(ast::ControlFlow::Return(expr)) => {
+                if let Some(expr) = expr {
+                    self.visit_expression(expr);
+                }
+            }
+            ast::Statement::Block(block) => self.visit_block(block),
+            ast::Statement::Error => (),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &'a ast::Expression) {
+        match expr {
+            ast::Expression::Ident(id) => {
+                self.reads.insert(self.bindings.id(id));
+            }
+            ast::Expression::Literal(_, _) => (),
+            ast::Expression::Unary(
+                ast::UnaryOperation::Minus(expr)
+                | ast::UnaryOperation::Not(expr)
+                | ast::UnaryOperation::Ref(expr),
+                _,
+            ) => self.visit_expression(expr),
+            ast::Expression::Binary(binary) => {
+                let (lhs, rhs) = binary.operands();
+                self.visit_expression(lhs);
+                self.visit_expression(rhs);
+            }
+            ast::Expression::Index(index) => {
+                self.visit_expression(&index.value);
+                self.visit_expression(&index.index);
+            }
+            ast::Expression::Call(call) => {
+                if !type_checker::is_builtin(&call.func_name.value) {
+                    let callee = self.bindings.id(&call.func_name);
+                    if let Some(caller) = self.function {
+                        self.calls.entry(caller).or_default().insert(callee);
+                    }
+                }
+                for arg in &call.args {
+                    self.visit_expression(arg);
+                }
+            }
+        }
+    }
+
+    fn const_value(&self, id: SymbolId) -> Option<(i64, ast::IntType)> {
+        let (expr, int_type) = self.consts.get(&id)?;
+        Some((self.eval(expr, *int_type)?, *int_type))
+    }
+
+    // Expressions that fail to evaluate were reported by the type checker
+    fn eval(&self, expr: &ast::Expression, int_type: ast::IntType) -> Option<i64> {
+        let lookup = |id: &ast::Ident| self.const_value(self.bindings.id(id));
+        const_eval::eval_int_with(&lookup, expr, int_type)
+            .ok()
+            .flatten()
+    }
+
+    fn empty_range(&mut self, start: &ast::Expression, end: &ast::Expression) {
+        let span = start.span().to(&end.span());
+        let bounds = (
+            self.eval(start, ast::IntType::I64),
+            self.eval(end, ast::IntType::I64),
+        );
+        let (Some(start), Some(end)) = bounds else {
+            return;
+        };
+        if start >= end {
+            let warning = Lint::EmptyRange
+                .warning(format!(
+                    "loop over the empty range {start}..{end} never runs"
+                ))
+                .with_primary(&span, "this range is empty")
+                .with_help("the loop runs while the variable is below the end of the range");
+            self.warn(warning);
+        }
+    }
+
+    fn unused_variables(&mut self) {
+        let unused: Vec<_> = self
+            .bindings
+            .symbols()
+            .filter(|(id, symbol)| {
+                symbol.kind != SymbolKind::Function
+                    && !symbol.name.starts_with('_')
+                    && !self.reads.contains(id)
+            })
+            .map(|(_, symbol)| {
+                Lint::UnusedVariable
+                    .warning(format!("unused {}: {}", symbol.kind, symbol.name))
+                    .with_primary(&symbol.span, "never read")
+                    .with_suggestion(
+                        &symbol.span,
+                        "if this is intentional, prefix it with an underscore",
+                        format!("_{}", symbol.name),
+                    )
+            })
+            .collect();
+        for warning in unused {
+            self.warn(warning);
+        }
+    }
+
+    /// Reports the functions not reachable from `main` or an exported
+    /// function
+    fn unused_functions(&mut self, block: &ast::Block) {
+        let functions = block.stmts.iter().filter_map(|stmt| match stmt {
+            ast::Statement::FuncDeclaration(func_decl) => Some(func_decl),
+            _ => None,
+        });
+        let mut reached = HashSet::new();
+        let mut pending: Vec<_> = functions
+            .clone()
+            .filter(|func_decl| func_decl.export || func_decl.name.value == "main")
+            .map(|func_decl| self.bindings.id(&func_decl.name))
+            .collect();
+        while let Some(id) = pending.pop() {
+            if reached.insert(id) {
+                pending.extend(self.calls.get(&id).into_iter().flatten());
+            }
+        }
+
+        let unused: Vec<_> = functions
+            .filter(|func_decl| {
+                !func_decl.name.value.starts_with('_')
+                    && !reached.contains(&self.bindings.id(&func_decl.name))
+            })
+            .map(|func_decl| self.bindings.get(&func_decl.name))
+            .map(|symbol| {
+                Lint::UnusedFunction
+                    .warning(format!(
+                        "function {} is never called from main or an exported function",
+                        symbol.name
+                    ))
+                    .with_primary(&symbol.span, "never called")
+                    .with_suggestion(
+                        &symbol.span,
+                        "if this is intentional, prefix it with an underscore",
+                        format!("_{}", symbol.name),
+                    )
+            })
+            .collect();
+        for warning in unused {
+            self.warn(warning);
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::{collections::HashMap, fmt::Display};
+
+use super::{ast, type_checker};
+use crate::diagnostics::Diagnostic;
+
+/// Unique ID of a declared variable or function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Const,
+    Parameter,
+    LoopVariable,
+    Function,
+}
+
+impl Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolKind::Variable => f.write_str("variable"),
+            SymbolKind::Const => f.write_str("const"),
+            SymbolKind::Parameter => f.write_str("parameter"),
+            SymbolKind::LoopVariable => f.write_str("loop variable"),
+            SymbolKind::Function => f.write_str("function"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// Name qualified by the declaring scope, e.g. `global::main::count`
+    pub path: String,
+    pub kind: SymbolKind,
+    pub mutability: ast::Mutability,
+    /// Where the name is declared
+    pub span: ast::Span,
+}
+
+/// Symbol every identifier of a resolved program refers to, keyed by the
+/// address of the identifier in the AST. Builtin function names are not
+/// bound.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    symbols: Vec<Symbol>,
+    bindings: HashMap<*const ast::Ident, SymbolId>,
+}
+
+impl Bindings {
+    pub fn id(&self, ident: &ast::Ident) -> SymbolId {
+        *self
+            .bindings
+            .get(&(ident as *const _))
+            .unwrap_or_else(|| panic!("identifier was not resolved: {}", ident.value))
+    }
+
+    pub fn get(&self, ident: &ast::Ident) -> &Symbol {
+        &self.symbols[self.id(ident).0]
+    }
+
+    /// Every declared symbol, in declaration order
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (SymbolId(i), symbol))
+    }
+
+    fn add(&mut self, symbol: Symbol) -> SymbolId {
+        self.symbols.push(symbol);
+        SymbolId(self.symbols.len() - 1)
+    }
+
+    fn bind(&mut self, ident: &ast::Ident, id: SymbolId) {
+        self.bindings.insert(ident as *const _, id);
+    }
+}
+
+/// Binds every identifier to its declaration. Undefined names, duplicate
+/// definitions and variables used before their declaration are all
+/// reported at once.
+pub fn resolve(block: &ast::Block) -> Result<Bindings, Vec<Diagnostic>> {
+    let mut resolver = Resolver::default();
+    resolver.visit_ast(block);
+    if resolver.errors.is_empty() {
+        Ok(resolver.bindings)
+    } else {
+        // Functions are declared ahead of everything else
+        resolver.errors.sort_by_key(Diagnostic::line);
+        Err(resolver.errors)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Resolver {
+    bindings: Bindings,
+    errors: Vec<Diagnostic>,
+    functions: HashMap<String, SymbolId>,
+    // Variables declared so far by path
+    variables: HashMap<String, SymbolId>,
+    // Every variable declaration of the program by path, including the ones
+    // not reached yet
+    declarations: HashMap<String, ast::Span>,
+}
+
+impl Resolver {
+    fn visit_ast(&mut self, block: &ast::Block) {
+        self.collect_declarations(block);
+
+        // Functions can be called before their declaration
+        for stmt in &block.stmts {
+            let name = match stmt {
+                ast::Statement::FuncDeclaration(func_decl) => &func_decl.name,
+                ast::Statement::ExternFuncDeclaration(extern_decl) => &extern_decl.name,
+                _ => continue,
+            };
+            self.declare_function(&block.scope, name);
+        }
+        self.visit_block(block);
+    }
+
+    fn collect_declarations(&mut self, block: &ast::Block) {
+        for stmt in &block.stmts {
+            let mut declare = |scope: &str, name: &ast::Ident| {
+                self.declarations
+                    .entry(format!("{scope}::{}", name.value))
+                    .or_insert_with(|| name.span.clone());
+            };
+            match stmt {
+                ast::Statement::VarDeclaration(var_decl) => declare(&block.scope, &var_decl.name),
+                ast::Statement::FuncDeclaration(func_decl) => {
+                    for arg in &func_decl.args {
+                        declare(&func_decl.body.scope, &arg.name);
+                    }
+                    self.collect_declarations(&func_decl.body);
+                }
+                ast::Statement::Loop(l) => {
+                    declare(&l.body.scope, &l.var);
+                    self.collect_declarations(&l.body);
+                }
+                ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => {
+                    self.collect_declarations(&_if.then);
+                    if let Some(otherwise) = &_if.otherwise {
+                        self.collect_declarations(otherwise);
+                    }
+                }
+                ast::Statement::Block(block) => self.collect_declarations(block),
+                _ => (),
+            }
+        }
+    }
+
+    fn visit_block(&mut self, block: &ast::Block) {
+        for stmt in &block.stmts {
+            self.visit_statement(stmt, &block.scope);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &ast::Statement, scope: &str) {
+        match statement {
+            ast::Statement::VarDeclaration(var_decl) => {
+                // The value cannot refer to the variable being declared
+                if let ast::RhsExpression::Expression(expr) = &var_decl.rhs {
+                    self.visit_expression(expr, scope);
+                }
+                let kind = match var_decl.declarion_type {
+                    ast::VarDeclarationType::Let => SymbolKind::Variable,
+                    ast::VarDeclarationType::Const => SymbolKind::Const,
+                };
+                self.declare_variable(scope, &var_decl.name, kind, var_decl.mutability);
+            }
+            ast::Statement::FuncDeclaration(func_decl) => self.visit_func_declaration(func_decl),
+            ast::Statement::ExternFuncDeclaration(extern_decl) => {
+                if let Some(id) = self.functions.get(&extern_decl.name.value) {
+                    self.bindings.bind(&extern_decl.name, *id);
+                }
+            }
+            ast::Statement::Loop(l) => {
+                match &l.iter {
+                    ast::LoopIter::Range { start, end } => {
+                        self.visit_expression(start, scope);
+                        self.visit_expression(end, scope);
+                    }
+                    ast::LoopIter::Collection(collection) => {
+                        self.visit_expression(collection, scope)
+                    }
+                }
+                self.declare_variable(
+                    &l.body.scope,
+                    &l.var,
+                    SymbolKind::LoopVariable,
+                    ast::Mutability::Immutable,
+                );
+                self.visit_block(&l.body);
+            }
+            ast::Statement::Assignment(assign) => {
+                if let ast::RhsExpression::Expression(expr) = &assign.rhs {
+                    self.visit_expression(expr, scope);
+                }
+                let name = &assign.variable_name;
+                let Some(id) = self.resolve_variable(scope, name) else {
+                    return;
+                };
+                let symbol = &self.bindings.symbols[id.0];
+                if symbol.mutability == ast::Mutability::Immutable {
+                    let help = match symbol.kind {
+                        SymbolKind::Const => {
+                            format!("declare {} with let to assign it", name.value)
+                        }
+                        _ => format!("copy {} into a variable declared with let", name.value),
+                    };
+                    let error = Diagnostic::error(
+                        "E0106",
+                        format!("Cannot assign to {} {}", symbol.kind, symbol.name),
+                    )
+                    .with_primary(&name.span, "cannot be assigned")
+                    .with_secondary(&symbol.span, format!("{} declared here", symbol.kind))
+                    .with_help(help);
+                    self.errors.push(error);
+                }
+            }
+            ast::Statement::Expression(expr) => self.visit_expression(expr, scope),
+            ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => {
+                self.visit_expression(&_if.condition, scope);
+                self.visit_block(&_if.then);
+                if let Some(otherwise) = &_if.otherwise {
+                    self.visit_block(otherwise);
+                }
+            }
+            ast::Statement::ControlFlow(ast::ControlFlow::Return(expr)) => {
+                if let Some(expr) = expr {
+                    self.visit_expression(expr, scope);
+                }
+            }
+            ast::Statement::Block(block) => self.visit_block(block),
+            ast::Statement::Error => (),
+        }
+    }
+
+    fn visit_func_declaration(&mut self, func_decl: &ast::FuncDeclaration) {
+        let name = &func_decl.name;
+        match self.functions.get(&name.value) {
+            Some(id) => self.bindings.bind(name, *id),
+            None => self.errors.push(
+                Diagnostic::error(
+                    "E0105",
+                    format!("Function {} must be declared at the top level", name.value),
+                )
+                .with_primary(&name.span, "declared inside another block"),
+            ),
+        }
+        for arg in &func_decl.args {
+            // Assigning to a reference parameter writes the variable it
+            // points to
+            let mutability = match arg._type.modifiers.is_empty() {
+                true => ast::Mutability::Immutable,
+                false => ast::Mutability::Mutable,
+            };
+            self.declare_variable(
+                &func_decl.body.scope,
+                &arg.name,
+                SymbolKind::Parameter,
+                mutability,
+            );
+        }
+        self.visit_block(&func_decl.body);
+    }
+
+    fn visit_expression(&mut self, expr: &ast::Expression, scope: &str) {
+        match expr {
+            ast::Expression::Ident(id) => {
+                self.resolve_variable(scope, id);
+            }
+            ast::Expression::Literal(_, _) => (),
+            ast::Expression::Unary(
+                ast::UnaryOperation::Minus(expr)
+                | ast::UnaryOperation::Not(expr)
+                | ast::UnaryOperation::Ref(expr),
+                _,
+            ) => self.visit_expression(expr, scope),
+            ast::Expression::Binary(binary) => {
+                let (lhs, rhs) = binary.operands();
+                self.visit_expression(lhs, scope);
+                self.visit_expression(rhs, scope);
+            }
+            ast::Expression::Index(index) => {
+                self.visit_expression(&index.value, scope);
+                self.visit_expression(&index.index, scope);
+            }
+            ast::Expression::Call(call) => {
+                let name = &call.func_name;
+                if !type_checker::is_builtin(&name.value) {
+                    match self.functions.get(&name.value) {
+                        Some(id) => self.bindings.bind(name, *id),
+                        None => self.errors.push(
+                            Diagnostic::error(
+                                "E0102",
+                                format!("undefined function: {}", name.value),
+                            )
+                            .with_primary(&name.span, "not found in this program"),
+                        ),
+                    }
+                }
+                for arg in &call.args {
+                    self.visit_expression(arg, scope);
+                }
+            }
+        }
+    }
+
+    fn duplicate(&mut self, id: SymbolId, name: &ast::Ident) {
+        let symbol = &self.bindings.symbols[id.0];
+        let error = Diagnostic::error("E0103", format!("Duplicate definition of {}", symbol.name))
+            .with_primary(&name.span, "defined again here")
+            .with_secondary(&symbol.span, "first defined here");
+        self.errors.push(error);
+    }
+
+    fn declare_function(&mut self, scope: &str, name: &ast::Ident) {
+        if let Some(id) = self.functions.get(&name.value) {
+            self.duplicate(*id, name);
+            return;
+        }
+        let id = self.bindings.add(Symbol {
+            name: name.value.clone(),
+            path: format!("{scope}::{}", name.value),
+            kind: SymbolKind::Function,
+            mutability: ast::Mutability::Immutable,
+            span: name.span.clone(),
+        });
+        self.bindings.bind(name, id);
+        self.functions.insert(name.value.clone(), id);
+    }
+
+    fn declare_variable(
+        &mut self,
+        scope: &str,
+        name: &ast::Ident,
+        kind: SymbolKind,
+        mutability: ast::Mutability,
+    ) {
+        let path = format!("{scope}::{}", name.value);
+        if let Some(id) = self.variables.get(&path) {
+            self.duplicate(*id, name);
+            return;
+        }
+        let id = self.bindings.add(Symbol {
+            name: name.value.clone(),
+            path: path.clone(),
+            kind,
+            mutability,
+            span: name.span.clone(),
+        });
+        self.bindings.bind(name, id);
+        self.variables.insert(path, id);
+    }
+
+    /// Binds `name` to the innermost variable declared so far
+    fn resolve_variable(&mut self, scope: &str, name: &ast::Ident) -> Option<SymbolId> {
+        let scopes = std::iter::successors(Some(scope), |scope| {
+            scope.rsplit_once("::").map(|(parent, _)| parent)
+        });
+        let paths: Vec<String> = scopes
+            .map(|scope| format!("{scope}::{}", name.value))
+            .collect();
+
+        if let Some(id) = paths
+            .iter()
+            .find_map(|path| self.variables.get(path))
+            .copied()
+        {
+            self.bindings.bind(name, id);
+            return Some(id);
+        }
+        match paths.iter().find_map(|path| self.declarations.get(path)) {
+            Some(declaration) => {
+                let error = Diagnostic::error(
+                    "E0104",
+                    format!("{} is used before its declaration", name.value),
+                )
+                .with_primary(&name.span, "used here")
+                .with_secondary(declaration, "declared here");
+                self.errors.push(error);
+            }
+            None => self.errors.push(
+                Diagnostic::error("E0101", format!("undefined variable: {}", name.value))
+                    .with_primary(&name.span, "not found in this scope"),
+            ),
+        }
+        None
+    }
+}
@@ -32,6 +32,15 @@ impl StackManager {
         self.block_bottoms.push(self.function_stack_size());
     }
 
+    /// Allocates `size` bytes on top of the current block
+    pub fn reserve(&mut self, size: usize) -> Vec<Mnemonic> {
+        if size == 0 {
+            return vec![];
+        }
+        self.grow_function_stack(size);
+        vec![SUB.op1(register::RSP).op2(size as u32)]
+    }
+
     pub fn free(&mut self) -> Vec<Mnemonic> {
         let code = if self.block_stack_size() > 0 {
             let local_size = self.block_stack_size();
@@ -70,25 +79,6 @@ impl StackManager {
         *top -= v;
     }
 
-    pub fn push_list(&mut self, data: &[u64], size: usize) -> Vec<Mnemonic> {
-        if data.len() == 0 {
-            return vec![];
-        }
-
-        let push_list = data.iter().fold(vec![], |mut acc: Vec<Mnemonic>, value| {
-            acc.extend(self.push(*value));
-            acc
-        });
-
-        let push_length = self.push(size as u64);
-        [push_list, push_length].concat()
-    }
-
-    pub fn push(&mut self, data: u64) -> Vec<Mnemonic> {
-        self.grow_function_stack(8);
-        vec![MOV.op1(register::RAX).op2(data), PUSH.op1(register::RAX)]
-    }
-
     pub fn push_register(&mut self, reg: register::Register) -> Vec<Mnemonic> {
         self.push_registers(&[reg])
     }
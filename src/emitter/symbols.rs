@@ -1,8 +1,8 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::emitter::variables::Value;
 
-use super::variables::{ValueLocation, Variable};
+use super::variables::{Function, ValueLocation, Variable};
 
 // pub enum Symbol{
 //     Variable(VariableSymbol),
@@ -132,6 +132,7 @@ impl SymbolResolver {
     pub fn resolve(
         &self,
         variables: &BTreeMap<String, Variable>,
+        functions: &HashMap<String, Function>,
         labels: &BTreeMap<String, usize>,
     ) -> Vec<Symbol> {
         let mut symbols = vec![];
@@ -149,10 +150,13 @@ impl SymbolResolver {
                     [
                         string_length.to_le_bytes().to_vec(),
                         string.clone().into_bytes(),
+                        // NUL terminated for C functions
+                        vec![0],
                     ]
                     .concat()
                 }
-                Value::Int(n) => n.to_le_bytes().to_vec(),
+                Value::Int(int_type, n) => n.to_le_bytes()[..int_type.size()].to_vec(),
+                Value::Char(c) => (*c as u32).to_le_bytes().to_vec(),
             };
             let value_size = data_bytes.len();
             symbols.push(Symbol::new(
@@ -167,12 +171,16 @@ impl SymbolResolver {
         }
 
         for (label, offset) in labels {
+            let scope = match functions.get(label) {
+                Some(function) if function.export => SymbolScope::Global,
+                _ => SymbolScope::Local,
+            };
             symbols.push(Symbol::new(
                 label.clone(),
                 *offset,
                 Section::Text,
                 SymbolType::Text,
-                SymbolScope::Local,
+                scope,
                 vec![],
             ));
         }
@@ -10,43 +10,69 @@ pub use code_context::*;
 
 use super::{
     ast::{self},
+    const_eval,
+    resolver::Bindings,
     stack::StackManager,
-    variables::{StackLocation, Value, ValueLocation, Variable},
+    type_checker::Types,
+    variables::{self, Function, Value, ValueLocation, Variable},
+    Emit,
 };
 use mnemonics::*;
 
 mod code_context;
+pub(super) mod format;
 mod stdlib;
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_code_context(
     block: &ast::Block,
     variables: &BTreeMap<String, Variable>,
-    scopes: &HashMap<String, Vec<String>>,
+    functions: &HashMap<String, Function>,
+    bindings: &Bindings,
+    types: &Types,
     image_base: u64,
+    emit: Emit,
+    checked: bool,
 ) -> CodeContext {
-    let mut text_builder = TextBuilder::new(variables, scopes, image_base);
-    text_builder.visit_ast(block);
+    let mut text_builder =
+        TextBuilder::new(variables, functions, bindings, types, image_base, checked);
+    text_builder.visit_ast(block, emit);
     text_builder.get_code_context()
 }
 
 pub struct TextBuilder {
     code_context: CodeContext,
     variables: BTreeMap<String, Variable>,
-    scopes: HashMap<String, Vec<String>>,
+    functions: HashMap<String, Function>,
+    bindings: Bindings,
+    types: Types,
     stack_manager: StackManager,
+    // Function being emitted and its returns waiting for the epilogue address
+    function: Option<String>,
+    return_jumps: Vec<usize>,
+    // Trap on division by zero and integer overflow
+    checked: bool,
 }
 
 impl TextBuilder {
     pub fn new(
         variables: &BTreeMap<String, Variable>,
-        scopes: &HashMap<String, Vec<String>>,
+        functions: &HashMap<String, Function>,
+        bindings: &Bindings,
+        types: &Types,
         image_base: u64,
+        checked: bool,
     ) -> Self {
         TextBuilder {
             code_context: CodeContext::new(image_base),
             variables: variables.clone(),
-            scopes: scopes.clone(),
+            functions: functions.clone(),
+            bindings: bindings.clone(),
+            types: types.clone(),
             stack_manager: StackManager::new(),
+            function: None,
+            return_jumps: vec![],
+            checked,
         }
     }
 
@@ -54,32 +80,48 @@ impl TextBuilder {
         self.code_context.clone()
     }
 
-    fn visit_ast(&mut self, block: &ast::Block) {
-        self.code_context.add_slice(&[
-            MOV.op1(register::RAX).op2(-16_i64 as u64),
-            AND.op1(register::RSP).op2(register::RAX),
-        ]);
+    fn visit_ast(&mut self, block: &ast::Block, emit: Emit) {
         self.stack_manager.init_stack();
 
-        let call = self.call("main");
-        self.code_context.add_slice(&call);
-
-        self.code_context
-            .add_slice(&self.stack_manager.align_for_call());
-        stdlib::exit(&mut self.code_context, 0);
+        // Object files are entered through their exported functions
+        if emit == Emit::Exe {
+            // argc, argv and envp are on the initial stack
+            self.code_context.add_slice(&[
+                MOV.op1(register::RAX)
+                    .op2(0_u64)
+                    .symbol(variables::STACK_BASE.to_string()),
+                store_int(register::RAX, register::RSP, 0, mem::size_of::<u64>()),
+                MOV.op1(register::RAX).op2(-16_i64 as u64),
+                AND.op1(register::RSP).op2(register::RAX),
+            ]);
+            stdlib::buffer_stdout(&mut self.code_context);
+
+            let call = self.call("main");
+            self.code_context.add_slice(&call);
+
+            // main's return value is the exit status
+            let returns_status = matches!(
+                self.functions
+                    .get("main")
+                    .map(|main| &main.return_type.name),
+                Some(ast::TypeName::Int(_))
+            );
+            if !returns_status {
+                self.code_context.add(MOV.op1(register::RAX).op2(0_u64));
+            }
+            self.code_context
+                .add_slice(&self.stack_manager.align_for_call());
+            stdlib::exit(&mut self.code_context, register::RAX);
+        }
 
         self.visit_block(block);
+        self.code_context.add_routines();
     }
 
     fn visit_block(&mut self, block: &ast::Block) {
-        self.stack_manager.init_stack();
-
-        let mnemonics = self.allocate_stack(&block);
-        self.code_context.add_slice(&mnemonics);
-        block.stmts.iter().for_each(|stmt| {
+        for stmt in &block.stmts {
             self.visit_statement(stmt, &block.scope);
-        });
-        self.code_context.add_slice(&self.stack_manager.free());
+        }
     }
 
     fn visit_statement(&mut self, statement: &ast::Statement, scope: &str) {
@@ -88,16 +130,23 @@ impl TextBuilder {
             ast::Statement::FuncDeclaration(func_decl) => {
                 self.visit_func_declaration(func_decl);
             }
+            ast::Statement::ExternFuncDeclaration(_) => (),
             ast::Statement::Expression(expr) => {
                 self.visit_expression(expr, scope);
             }
-            ast::Statement::VarDeclaration(_) => (),
+            ast::Statement::VarDeclaration(var_decl) => {
+                self.visit_var_declaration(var_decl, scope);
+            }
             ast::Statement::Assignment(assign) => {
                 self.visit_assignment(assign, scope);
             }
             ast::Statement::Block(stmts) => (),
-            ast::Statement::Loop(l) => self.visit_loop(l, scope),
-            ast::Statement::ControlFlow(_) => (),
+            ast::Statement::Error => unreachable!("programs with syntax errors are not compiled"),
+            ast::Statement::Loop(l) => self.visit_loop(l),
+            ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => self.visit_if(_if, scope),
+            ast::Statement::ControlFlow(ast::ControlFlow::Return(expr)) => {
+                self.visit_return(expr.as_ref(), scope)
+            }
         };
     }
 
@@ -107,26 +156,29 @@ impl TextBuilder {
             args,
             return_type,
             body,
+            ..
         } = func_decl;
 
+        let locals_size = self.functions[&name.value].locals_size;
+
         self.code_context.set_label(name.value.clone());
         self.code_context.add_slice(
             &self
                 .stack_manager
                 .init_function_stack(&abi::ARG_REGISTERS[..args.len()]),
         );
+        self.stack_manager.init_stack();
+        self.code_context
+            .add_slice(&self.stack_manager.reserve(locals_size as usize));
 
-        for (i, arg) in args.iter().enumerate() {
-            let variable = self
-                .get_variable_mut(&func_decl.body.scope, &arg.name.value)
-                .unwrap();
-            variable.value_loc = ValueLocation::Stack(StackLocation::Function(
-                (mem::size_of::<u64>() * (i + 1)) as u64,
-            ));
-        }
-
+        self.function = Some(name.value.clone());
         self.visit_block(body);
+        self.function = None;
 
+        for jump in mem::take(&mut self.return_jumps) {
+            self.code_context.patch_jump(jump);
+        }
+        self.code_context.add_slice(&self.stack_manager.free());
         self.code_context.add_slice(
             &self
                 .stack_manager
@@ -135,15 +187,52 @@ impl TextBuilder {
         self.code_context.add(RET.no_op());
     }
 
+    fn visit_var_declaration(&mut self, var_decl: &ast::VarDeclaration, scope: &str) {
+        let expr = match &var_decl.rhs {
+            ast::RhsExpression::Expression(expr) => expr,
+            ast::RhsExpression::Block(_) => unreachable!("the parser builds no block expressions"),
+        };
+
+        let variable = self.variable(&var_decl.name).clone();
+
+        // Consts are already in the data section
+        if matches!(variable.value_loc, ValueLocation::DataSection(_)) {
+            return;
+        }
+        if let ast::Expression::Literal(ast::Literal::String(s), _) = expr {
+            self.store_string(&variable, s);
+            return;
+        }
+        if let Value::String(_) = variable.value_type {
+            self.visit_string_expression(expr, scope);
+            self.store_pointer(&variable);
+            return;
+        }
+
+        let int_type = variable
+            .value_type
+            .int_type()
+            .unwrap_or_else(|| panic!("Expected integer variable: {variable:#?}"));
+        self.visit_int_expression(expr, scope, int_type);
+        self.store_int_variable(&variable);
+    }
+
     fn visit_expression(&mut self, expr: &ast::Expression, scope: &str) {
-        match expr {
-            ast::Expression::Call(call) => {
-                self.visit_call(call, scope);
+        let ast::Expression::Call(call) = expr else {
+            return;
+        };
+        // Builtins called for their side effect only
+        match call.func_name.value.as_str() {
+            "read" | "read_int" | "open" | "close" | "write" | "write_file" => {
+                self.visit_int_expression(expr, scope, ast::IntType::I64)
             }
-            _ => (),
+            "read_line" | "read_file" => self.visit_string_expression(expr, scope),
+            _ => self.visit_call(call, scope),
         }
     }
 
+    /// Stores the value into the variable, the type checker made sure they
+    /// have the same type
     fn visit_assignment(&mut self, assign: &ast::Assignment, scope: &str) {
         let ast::Assignment {
             variable_name: id,
@@ -152,169 +241,280 @@ impl TextBuilder {
 
         let expr = match rhs {
             ast::RhsExpression::Expression(expr) => expr,
-            ast::RhsExpression::Block(block) => todo!(),
+            ast::RhsExpression::Block(_) => unreachable!("the parser builds no block expressions"),
         };
+        let variable = self.variable(id).clone();
 
-        match expr {
-            ast::Expression::Literal(lit) => {
-                let variable = self
-                    .get_variable(&scope, &id.value)
-                    .unwrap_or_else(|| panic!("undefined variable: {}", id.value))
-                    .clone();
-                if matches!(variable.value_loc, ValueLocation::DataSection(_)) {
-                    panic!("Cannot assign to const data: {variable:#?}");
-                }
-                let lit_data_type: Value = lit.clone().into();
-                if std::mem::discriminant(&variable.value_type)
-                    != std::mem::discriminant(&lit_data_type)
-                {
-                    panic!(
-                        "Cannot assign {:?} to {:?}",
-                        variable.value_type, lit_data_type
-                    );
-                }
-                self.code_context.add_slice(&match lit {
-                    ast::Literal::String(s) => {
-                        self.stack_manager.push_list(&str_to_u64(s), s.len())
-                    }
-                    ast::Literal::Integer(n) => self.stack_manager.push(n.value as u64),
-                });
-
-                let value_loc = self.stack_manager.function_stack_size();
-
-                let mut value_size = variable.value_size;
-                let remainder = value_size % 8;
-                if remainder != 0 {
-                    value_size += 8 - remainder;
+        match (&variable.value_type, expr) {
+            (Value::String(_), ast::Expression::Literal(ast::Literal::String(s), _)) => {
+                self.store_string(&variable, s);
+            }
+            (Value::String(_), _) => {
+                if !variable.reference {
+                    panic!("Cannot assign a computed string to {variable:#?}");
                 }
-
-                let variable = self
-                    .get_variable_mut(&scope, &id.value)
-                    .unwrap_or_else(|| panic!("undefined variable: {}", id.value));
-                variable.value_size = value_size;
-                variable.value_loc = ValueLocation::Stack(StackLocation::Block(value_loc as u64));
+                self.visit_string_expression(expr, scope);
+                self.store_pointer(&variable);
             }
-            ast::Expression::Ident(_) => todo!(),
-            ast::Expression::Call(_) => todo!(),
-            ast::Expression::Unary(unary_operation) => todo!(),
-            ast::Expression::Binary(binary_operation) => todo!(),
-        };
+            (value, _) => {
+                let int_type = value
+                    .int_type()
+                    .unwrap_or_else(|| panic!("Expected integer variable: {variable:#?}"));
+                self.visit_int_expression(expr, scope, int_type);
+                self.store_int_variable(&variable);
+            }
+        }
     }
 
     fn visit_call(&mut self, call: &ast::Call, scope: &str) {
-        if call.func_name.value == "print" {
-            let (id, reference) = match call.args.first() {
-                Some(ast::Expression::Ident(id)) => (id, false),
-                Some(ast::Expression::Unary(unary)) => match unary {
-                    ast::UnaryOperation::Ref(id) => match id.as_ref() {
-                        ast::Expression::Ident(id) => (id, true),
-                        _ => panic!("Function print expects an identifier"),
-                    },
-                    _ => panic!("Function print expects a reference"),
-                },
-                _ => panic!("Function print expects one argument"),
+        if call.func_name.value == "flush" {
+            if !call.args.is_empty() {
+                panic!("Function flush expects no arguments");
+            }
+            stdlib::flush(&mut self.code_context);
+            return;
+        }
+        if call.func_name.value == "exit" {
+            let [code] = call.args.as_slice() else {
+                panic!("Function exit expects one argument");
             };
+            let int_type = self.int_type_of(code).unwrap_or(ast::IntType::I64);
+            self.visit_int_expression(code, scope, int_type);
+            stdlib::exit(&mut self.code_context, register::RAX);
+            return;
+        }
+        if call.func_name.value == "panic" {
+            let [message] = call.args.as_slice() else {
+                panic!("Function panic expects one argument");
+            };
+            self.visit_string_expression(message, scope);
+            self.code_context
+                .add(MOV.op1(register::RSI).op2(register::RAX));
+            stdlib::panic(&mut self.code_context, &call.span.to_string(), None);
+            return;
+        }
+        if call.func_name.value == "assert" {
+            let [condition] = call.args.as_slice() else {
+                panic!("Function assert expects one argument");
+            };
+            let int_type = self.int_type_of(condition).unwrap_or(ast::IntType::I64);
+            self.visit_int_expression(condition, scope, int_type);
+            self.code_context.add(CMP.op1(register::RAX).op2(0_u32));
+            self.code_context.add(JNE.op1(Operand::Offset32(0)));
+            let holds = self.code_context.get_pc() - 1;
+            stdlib::panic(
+                &mut self.code_context,
+                &call.span.to_string(),
+                Some("assertion failed"),
+            );
+            self.code_context.patch_jump(holds);
+            return;
+        }
+        if matches!(
+            call.func_name.value.as_str(),
+            "print" | "eprint" | "println" | "eprintln"
+        ) {
+            let name = call.func_name.value.as_str();
+            let stream = match name {
+                "eprint" | "eprintln" => stdlib::Stream::Stderr,
+                _ => stdlib::Stream::Stdout,
+            };
+            let newline = name.ends_with("ln");
+            match call.args.as_slice() {
+                [] if newline => (),
+                [ast::Expression::Literal(ast::Literal::String(format), _), args @ ..] => {
+                    self.visit_format(format, args, scope, stream)
+                }
+                [arg] => self.visit_print(arg, scope, stream),
+                _ => panic!("Function {name} expects a format string and its arguments"),
+            }
+            if newline {
+                stdlib::print_str(&mut self.code_context, stream, "\n");
+            }
+            return;
+        } else {
+            self.visit_function_call(call, scope);
+        }
+    }
 
-            let mut variable = self
-                .get_variable(&scope, &id.value)
-                .unwrap_or_else(|| panic!("undefined variable: {}::{}", &scope, id.value))
-                .clone();
-
-            variable.reference = reference;
-
-            match variable.value_type {
-                Value::String(_) => {
-                    let args = vec![variable.clone()];
+    /// Prints `format` with its placeholders replaced by `args`
+    fn visit_format(
+        &mut self,
+        format: &str,
+        args: &[ast::Expression],
+        scope: &str,
+        stream: stdlib::Stream,
+    ) {
+        let pieces = format::parse(format);
+        let placeholders = pieces
+            .iter()
+            .filter(|piece| matches!(piece, format::Piece::Arg(_)))
+            .count();
+        if placeholders != args.len() {
+            panic!(
+                "Format string {format:?} expects {placeholders} arguments, found {}",
+                args.len()
+            );
+        }
 
-                    abi::push_args(
-                        &mut self.code_context,
-                        &mut self.stack_manager,
-                        args.as_slice(),
-                    );
-                    self.code_context
-                        .add_slice(&self.stack_manager.align_for_call());
+        let mut args = args.iter();
+        for piece in pieces {
+            let arg = match piece {
+                format::Piece::Text(text) => {
+                    stdlib::print_str(&mut self.code_context, stream, &text);
+                    continue;
+                }
+                format::Piece::Arg(_) => args.next().unwrap(),
+            };
+            let format::Piece::Arg(Some(int_format)) = piece else {
+                self.visit_print(arg, scope, stream);
+                continue;
+            };
+            if self.is_string_expression(arg) || self.is_char_expression(arg) {
+                panic!("Only integers can be laid out in format string {format:?}");
+            }
+            let int_type = self.int_type_of(arg).unwrap_or(ast::IntType::I64);
+            self.visit_int_expression(arg, scope, int_type);
+            // Hex digits show the bits of the type
+            if int_format.hex {
+                if let Some(extend) = extend_int(register::RAX, int_type.size(), false) {
+                    self.code_context.add(extend);
+                }
+            }
+            let int_format = stdlib::IntFormat {
+                signed: int_type.is_signed() && !int_format.hex,
+                ..int_format
+            };
+            self.print_formatted_int(int_format, stream);
+        }
+    }
 
-                    stdlib::print(&mut self.code_context);
+    /// Prints a single string, char or integer
+    fn visit_print(&mut self, arg: &ast::Expression, scope: &str, stream: stdlib::Stream) {
+        if let ast::Expression::Literal(ast::Literal::String(s), _) = arg {
+            stdlib::print_str(&mut self.code_context, stream, s);
+            return;
+        }
+        if self.is_char_expression(arg) {
+            self.visit_int_expression(arg, scope, ast::IntType::U32);
+            self.print_char(stream);
+            return;
+        }
+        if !self.is_string_expression(arg) {
+            let int_type = self.int_type_of(arg).unwrap_or(ast::IntType::I64);
+            self.visit_int_expression(arg, scope, int_type);
+            self.print_int(int_type, stream);
+            return;
+        }
+        // Inline bytes and pointers are told apart by load_address
+        self.visit_string_expression(arg, scope);
+        self.print_string(stream);
+    }
 
+    /// Calls a user function, the return value is left in RAX
+    fn visit_function_call(&mut self, call: &ast::Call, scope: &str) {
+        let name = &call.func_name.value;
+        let function = self
+            .functions
+            .get(name)
+            .unwrap_or_else(|| panic!("undefined function: {name}"))
+            .clone();
+        let registers = abi::ARG_REGISTERS
+            .get(..call.args.len())
+            .unwrap_or_else(|| panic!("more arguments than registers: {name}"));
+
+        // C has its own stdout buffer, keep the output in order
+        if function.external {
+            stdlib::flush(&mut self.code_context);
+        }
+        self.code_context
+            .add_slice(&self.stack_manager.push_registers(registers));
+
+        // Arguments are evaluated onto the stack first, evaluating one may
+        // call another function
+        for (arg, expr) in function.args.iter().zip(&call.args) {
+            if arg._type.name == ast::TypeName::String {
+                self.visit_string_expression(expr, scope);
+                // C expects a pointer to the NUL terminated bytes
+                if function.external {
                     self.code_context
-                        .add_slice(&self.stack_manager.unalign_after_call());
-
-                    abi::pop_args(&mut self.code_context, &mut self.stack_manager, args.len());
+                        .add(ADD.op1(register::RAX).op2(mem::size_of::<u64>() as u32));
                 }
-                Value::Int(n) => {
-                    let args = &[variable.clone()];
-
-                    abi::push_args(&mut self.code_context, &mut self.stack_manager, args);
+            } else if !arg._type.modifiers.is_empty() {
+                self.load_address(expr, register::RAX);
+            } else {
+                let int_type = Value::zero(&arg._type.name)
+                    .int_type()
+                    .expect("parameters are strings, ints or chars");
+                self.visit_int_expression(expr, scope, int_type);
+            }
+            self.code_context
+                .add_slice(&self.stack_manager.push_register(register::RAX));
+        }
+        abi::pop_args(
+            &mut self.code_context,
+            &mut self.stack_manager,
+            registers.len(),
+        );
 
-                    self.code_context
-                        .add_slice(&self.stack_manager.align_for_call());
+        if function.external {
+            // Variadic C functions read the number of vector registers from AL
+            self.code_context
+                .add(XOR.op1(register::RAX).op2(register::RAX));
+        }
+        let call_code = self.call(name);
+        self.code_context.add_slice(&call_code);
 
-                    stdlib::printd(&mut self.code_context);
+        abi::pop_args(
+            &mut self.code_context,
+            &mut self.stack_manager,
+            registers.len(),
+        );
 
-                    self.code_context
-                        .add_slice(&self.stack_manager.unalign_after_call());
+        // C leaves the upper bits of narrow return values undefined
+        if let ast::TypeName::Int(int_type) = function.return_type.name {
+            if let Some(extend) = extend_int(register::RAX, int_type.size(), int_type.is_signed()) {
+                self.code_context.add(extend);
+            }
+        }
+        // and returns strings as a pointer to the NUL terminated bytes
+        if function.external && function.return_type.name == ast::TypeName::String {
+            self.code_context
+                .add(MOV.op1(register::RSI).op2(register::RAX));
+            stdlib::from_c_string(&mut self.code_context);
+        }
+    }
 
-                    abi::pop_args(&mut self.code_context, &mut self.stack_manager, args.len());
-                }
-            };
+    fn visit_loop(&mut self, l: &ast::Loop) {
+        let (start, end) = match &l.iter {
+            ast::LoopIter::Range { start, end } => {
+                let bound = |expr| {
+                    const_eval::eval_int(&self.variables, &self.bindings, expr, ast::IntType::I64)
+                        .unwrap_or_else(|error| panic!("{}", error.message))
+                        .unwrap_or_else(|| panic!("Loop bounds must be known at compile time"))
+                };
+                (bound(start), bound(end))
+            }
+            ast::LoopIter::Collection(collection) => {
+                return self.visit_collection_loop(l, collection)
+            }
+        };
 
+        // The body is emitted as a do-while loop
+        if start >= end {
             return;
-        } else {
-            let args: Vec<Variable> = call
-                .args
-                .iter()
-                .flat_map(|expr| {
-                    let (id, reference) = match expr {
-                        ast::Expression::Ident(id) => (id, false),
-                        ast::Expression::Unary(unary) => match unary {
-                            ast::UnaryOperation::Ref(id) => match id.as_ref() {
-                                ast::Expression::Ident(id) => (id, true),
-                                _ => panic!("Function print expects an identifier"),
-                            },
-                            _ => panic!("Function print expects a reference"),
-                        },
-
-                        _ => todo!(),
-                    };
-                    let mut variable = self
-                        .get_variable(&scope, &id.value)
-                        .unwrap_or_else(|| panic!("undefined symbol: {}", id.value))
-                        .clone();
-
-                    variable.reference = reference;
-
-                    vec![variable]
-                })
-                .collect();
-
-            abi::push_args(
-                &mut self.code_context,
-                &mut self.stack_manager,
-                args.as_slice(),
-            );
-
-            let call_code = self.call(&call.func_name.value);
-            self.code_context.add_slice(&call_code);
-            abi::pop_args(&mut self.code_context, &mut self.stack_manager, args.len());
         }
-    }
-
-    fn visit_loop(&mut self, l: &ast::Loop, scope: &str) {
-        self.stack_manager.init_stack();
+        let (start, end) = (start as u64, end as u64);
 
         let block = &l.body;
 
-        let mnemonics = self.allocate_stack(&block);
-        self.code_context.add_slice(&mnemonics);
-        let offset = self.code_context.get_code_size();
+        let counter = self.variable(&l.var).clone();
+        self.code_context.add(MOV.op1(register::RAX).op2(start));
+        self.store_int_variable(&counter);
 
-        block.stmts.iter().for_each(|stmt| {
-            self.visit_statement(stmt, &block.scope);
-        });
+        let offset = self.code_context.get_code_size();
 
-        let counter = self
-            .get_variable(&l.body.scope, &l.var.value)
-            .unwrap_or_else(|| panic!("undefined variable: {}::{}", l.body.scope, l.var.value));
+        self.visit_block(block);
 
         let value_loc: u32 = match &counter.value_loc {
             ValueLocation::Stack(stack_location) => stack_location.into(),
@@ -328,71 +528,756 @@ impl TextBuilder {
             .add(INC.op1(register::RCX).disp(Operand::Offset32(0)));
         self.code_context.add(
             CMP.op1(register::RCX)
-                .op2(l.end as u32)
+                .op2(end as u32)
                 .disp(Operand::Offset32(0)),
         );
 
         let jump = JL.op1(Operand::Offset32(-(0 as i32))).as_vec().len()
             + self.code_context.get_code_size()
             - offset;
-        dbg!(jump);
         self.code_context
             .add(JL.op1(Operand::Offset32(-(jump as i32))));
+    }
 
-        self.code_context.add_slice(&self.stack_manager.free());
+    /// Iterates over the chars of a string, decoding one UTF-8 sequence per
+    /// iteration
+    fn visit_collection_loop(&mut self, l: &ast::Loop, collection: &ast::Expression) {
+        let block = &l.body;
+
+        let var = self.variable(&l.var).clone();
+        let cursor = self
+            .variables
+            .get(&variables::loop_cursor(&block.scope, &l.var.value))
+            .unwrap_or_else(|| panic!("missing cursor of loop variable {}", l.var.value))
+            .clone();
+        self.code_context.add(MOV.op1(register::RAX).op2(0_u64));
+        self.store_int_variable(&cursor);
+
+        let loop_start = self.code_context.get_code_size();
+        self.load_address(collection, register::RCX);
+        self.load_int_variable(&cursor, register::RAX);
+        self.code_context.add(
+            CMP.op1(register::RAX)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(0)),
+        );
+        self.code_context.add(JAE.op1(Operand::Offset32(0)));
+        let exit_jump = self.code_context.get_pc() - 1;
+
+        self.code_context.add_slice(&[
+            ADD.op1(register::RCX).op2(register::RAX),
+            ADD.op1(register::RCX).op2(mem::size_of::<u64>() as u32),
+        ]);
+        stdlib::utf8::decode(&mut self.code_context, register::RCX);
+        self.store_int_variable(&var);
+
+        // Advance the cursor by the length of the decoded sequence
+        self.code_context
+            .add(MOV.op1(register::RAX).op2(register::RDX));
+        self.load_int_variable(&cursor, register::RCX);
+        self.code_context
+            .add(ADD.op1(register::RAX).op2(register::RCX));
+        self.store_int_variable(&cursor);
+
+        self.visit_block(block);
+
+        let jump = JMP_REL.op1(Operand::Offset32(0)).as_vec().len()
+            + self.code_context.get_code_size()
+            - loop_start;
+        self.code_context
+            .add(JMP_REL.op1(Operand::Offset32(-(jump as i32))));
+        self.code_context.patch_jump(exit_jump);
+    }
+
+    fn visit_if(&mut self, _if: &ast::If, scope: &str) {
+        let int_type = self
+            .int_type_of(&_if.condition)
+            .unwrap_or(ast::IntType::I64);
+        self.visit_int_expression(&_if.condition, scope, int_type);
+        self.code_context.add(CMP.op1(register::RAX).op2(0_u32));
+        self.code_context.add(JE.op1(Operand::Offset32(0)));
+        let else_jump = self.code_context.get_pc() - 1;
+
+        self.visit_block(&_if.then);
+
+        match &_if.otherwise {
+            Some(otherwise) => {
+                self.code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+                let end_jump = self.code_context.get_pc() - 1;
+                self.code_context.patch_jump(else_jump);
+                self.visit_block(otherwise);
+                self.code_context.patch_jump(end_jump);
+            }
+            None => self.code_context.patch_jump(else_jump),
+        }
     }
 
-    fn allocate_stack(&mut self, stmts: &ast::Block) -> Vec<Mnemonic> {
-        let ids: Vec<String> = match self.scopes.get(&stmts.scope) {
-            Some(symbols) => symbols.clone(),
-            None => return vec![],
+    /// Leaves the return value in RAX and jumps to the function epilogue
+    fn visit_return(&mut self, expr: Option<&ast::Expression>, scope: &str) {
+        let name = self
+            .function
+            .clone()
+            .unwrap_or_else(|| panic!("return outside of a function"));
+
+        // The type checker matched the value with the return type
+        if let Some(expr) = expr {
+            let type_name = &self.functions[&name].return_type.name;
+            match Value::zero(type_name).int_type() {
+                Some(int_type) => self.visit_int_expression(expr, scope, int_type),
+                None => self.visit_return_string(expr, scope),
+            }
+        }
+
+        self.code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        self.return_jumps.push(self.code_context.get_pc() - 1);
+    }
+
+    /// Leaves the address of the returned string in RAX. Strings held in the
+    /// frame are gone after the return, they are copied to the heap.
+    fn visit_return_string(&mut self, expr: &ast::Expression, scope: &str) {
+        self.visit_string_expression(expr, scope);
+        let in_frame = matches!(
+            expr,
+            ast::Expression::Ident(_) | ast::Expression::Unary(ast::UnaryOperation::Ref(_), _)
+        ) && {
+            let variable = self.expression_variable(expr);
+            matches!(variable.value_loc, ValueLocation::Stack(_)) && !variable.reference
         };
+        if in_frame {
+            stdlib::copy_string(&mut self.code_context);
+        }
+    }
 
-        dbg!(&ids);
-
-        let mut code = vec![];
-        for id in ids.iter() {
-            let data = self.variables.get(id).unwrap();
-            dbg!(data);
-            code.extend(match &data.value_loc {
-                ValueLocation::Stack(stack_loc) => match stack_loc {
-                    StackLocation::Block(_) => {
-                        let code = match &data.value_type {
-                            Value::String(s) => {
-                                self.stack_manager.push_list(&str_to_u64(s), s.len())
-                            }
-                            Value::Int(i) => self.stack_manager.push(*i as u64),
-                        };
-                        dbg!(self.stack_manager.function_stack_size());
-                        self.variables.get_mut(id).unwrap().value_loc = ValueLocation::Stack(
-                            StackLocation::Block(self.stack_manager.function_stack_size() as u64),
-                        );
-                        code
-                    }
-                    StackLocation::Function(_) => vec![],
-                },
-                ValueLocation::DataSection(_) => vec![],
-            });
-        }
-        // dbg!(self.stack_manager.get_top());
-        code
+    /// Evaluates an integer expression into RAX, truncated to `int_type` and
+    /// extended back to 64 bits. The expression is computed with the type the
+    /// type checker gave it and only then converted to `int_type`.
+    fn visit_int_expression(
+        &mut self,
+        expr: &ast::Expression,
+        scope: &str,
+        int_type: ast::IntType,
+    ) {
+        let expr_type = self.int_type_of(expr).unwrap_or(int_type);
+
+        // Constant expressions fold into a single immediate
+        let folded = const_eval::eval_int(&self.variables, &self.bindings, expr, expr_type)
+            .unwrap_or_else(|error| panic!("{}", error.message));
+        if let Some(n) = folded {
+            self.code_context.add(MOV.op1(register::RAX).op2(n as u64));
+            self.convert_int(expr_type, int_type);
+            return;
+        }
+        match expr {
+            ast::Expression::Literal(ast::Literal::Integer(n), _) => {
+                self.code_context.add(MOV.op1(register::RAX).op2(n.value));
+            }
+            ast::Expression::Literal(ast::Literal::Char(c), _) => {
+                self.code_context.add(MOV.op1(register::RAX).op2(*c as u64));
+            }
+            ast::Expression::Ident(id) => {
+                let variable = self.variable(id).clone();
+                self.load_int_variable(&variable, register::RAX);
+            }
+            // Strings are indexed by byte
+            ast::Expression::Index(index) => {
+                self.visit_int_expression(&index.index, scope, ast::IntType::U64);
+                self.code_context
+                    .add_slice(&self.stack_manager.push_register(register::RAX));
+                self.load_address(&index.value, register::RCX);
+                self.code_context
+                    .add_slice(&self.stack_manager.pop_register(register::RAX));
+                self.code_context.add(
+                    CMP.op1(register::RAX)
+                        .op2(register::RCX)
+                        .disp(Operand::Offset32(0)),
+                );
+                self.code_context.add(JB.op1(Operand::Offset32(0)));
+                let in_bounds = self.code_context.get_pc() - 1;
+                stdlib::panic(
+                    &mut self.code_context,
+                    &index.span.to_string(),
+                    Some("index out of bounds"),
+                );
+                self.code_context.patch_jump(in_bounds);
+                self.code_context.add_slice(&[
+                    ADD.op1(register::RCX).op2(register::RAX),
+                    load_int(
+                        register::RAX,
+                        register::RCX,
+                        mem::size_of::<u64>() as i32,
+                        1,
+                        false,
+                    ),
+                ]);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "arg_count" => {
+                stdlib::arg_count(&mut self.code_context);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "read_int" => {
+                stdlib::read_int(&mut self.code_context);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "file_error" => {
+                stdlib::file_error(&mut self.code_context);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "read" => {
+                self.visit_read(call, scope);
+            }
+            ast::Expression::Call(call)
+                if matches!(
+                    call.func_name.value.as_str(),
+                    "open" | "close" | "write" | "write_file"
+                ) =>
+            {
+                self.visit_file_call(call, scope);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "len" => {
+                let [string] = call.args.as_slice() else {
+                    panic!("Function len expects one argument");
+                };
+                self.visit_string_expression(string, scope);
+                self.code_context.add(
+                    MOV.op1(register::RAX)
+                        .op2(register::RAX)
+                        .disp(Operand::Offset32(0)),
+                );
+            }
+            ast::Expression::Call(call) => self.visit_function_call(call, scope),
+            ast::Expression::Unary(ast::UnaryOperation::Minus(inner), span) => {
+                self.visit_int_expression(inner, scope, expr_type);
+                self.code_context.add(NEG.op1(register::RAX));
+                if self.checked {
+                    self.check_overflow(expr_type, false, span);
+                }
+            }
+            ast::Expression::Binary(binary) => {
+                let (lhs, rhs) = binary.operands();
+                // comparisons yield 0 or 1, the operands keep their own type
+                let operand_type = if binary.is_comparison() {
+                    self.int_type_of(lhs).unwrap_or(ast::IntType::I64)
+                } else {
+                    expr_type
+                };
+
+                self.visit_int_expression(lhs, scope, operand_type);
+                self.code_context
+                    .add_slice(&self.stack_manager.push_register(register::RAX));
+                self.visit_int_expression(rhs, scope, operand_type);
+                self.code_context
+                    .add(MOV.op1(register::RCX).op2(register::RAX));
+                self.code_context
+                    .add_slice(&self.stack_manager.pop_register(register::RAX));
+
+                if self.checked {
+                    self.visit_checked_binary(binary, operand_type, &expr.span());
+                } else {
+                    self.code_context
+                        .add_slice(&binary_operation(binary, operand_type));
+                }
+            }
+            _ => panic!("Expected integer expression, found: {expr:?}"),
+        }
+
+        if let Some(extend) = extend_int(register::RAX, expr_type.size(), expr_type.is_signed()) {
+            self.code_context.add(extend);
+        }
+        self.convert_int(expr_type, int_type);
     }
 
-    fn get_variable(&self, scope: &str, id: &str) -> Option<&Variable> {
-        let id_path = format!("{}::{}", scope, id);
-        // dbg!(&scope, &id);
+    /// Converts the value of type `from` in RAX to `to`
+    fn convert_int(&mut self, from: ast::IntType, to: ast::IntType) {
+        if from == to {
+            return;
+        }
+        if let Some(extend) = extend_int(register::RAX, to.size(), to.is_signed()) {
+            self.code_context.add(extend);
+        }
+    }
 
-        self.variables.get(&id_path).or_else(|| {
-            let (parent_scope, _) = scope.rsplit_once("::").unwrap();
-            self.get_variable(parent_scope, id)
-        })
+    /// Emits `binary` on RAX and RCX, trapping at `span` on a zero divisor
+    /// and on results that don't fit `int_type`
+    fn visit_checked_binary(
+        &mut self,
+        binary: &ast::BinaryOperation,
+        int_type: ast::IntType,
+        span: &ast::Span,
+    ) {
+        use ast::BinaryOperation::*;
+
+        match binary {
+            Div(..) | Rem(..) => {
+                self.code_context.add(CMP.op1(register::RCX).op2(0_u32));
+                self.trap(&JE, "division by zero", span);
+                // MIN / -1 doesn't fit, and faults in IDIV for i64. The
+                // operands are sign extended, compare them as i64
+                if int_type.is_signed() {
+                    let min = i64::MIN >> (64 - 8 * int_type.size());
+                    self.code_context.add_slice(&[
+                        MOV.op1(register::RDX).op2(u64::MAX),
+                        CMP.op1(register::RCX).op2(register::RDX),
+                    ]);
+                    self.code_context.add(JNE.op1(Operand::Offset32(0)));
+                    let divisor_ok = self.code_context.get_pc() - 1;
+                    self.code_context.add_slice(&[
+                        MOV.op1(register::RDX).op2(min as u64),
+                        CMP.op1(register::RAX).op2(register::RDX),
+                    ]);
+                    self.trap(&JE, "arithmetic overflow", span);
+                    self.code_context.patch_jump(divisor_ok);
+                }
+                self.code_context
+                    .add_slice(&binary_operation(binary, int_type));
+            }
+            Mul(..) if int_type == ast::IntType::U64 => {
+                // IMUL only flags signed overflow, MUL flags a non-zero RDX
+                self.code_context.add(MUL.op1(register::RCX));
+                self.check_overflow(int_type, true, span);
+            }
+            Plus(..) | Minus(..) | Mul(..) => {
+                self.code_context
+                    .add_slice(&binary_operation(binary, int_type));
+                self.check_overflow(int_type, true, span);
+            }
+            _ => self
+                .code_context
+                .add_slice(&binary_operation(binary, int_type)),
+        }
+    }
+
+    /// Traps unless the result in RAX fits `int_type`. 64-bit results are
+    /// checked with the flags of the last operation, `carry` tells whether
+    /// an unsigned overflow shows up in CF rather than OF
+    fn check_overflow(&mut self, int_type: ast::IntType, carry: bool, span: &ast::Span) {
+        match int_type {
+            ast::IntType::I64 => self.trap(&JO, "arithmetic overflow", span),
+            ast::IntType::U64 if carry => self.trap(&JB, "arithmetic overflow", span),
+            // NEG sets CF for any non-zero operand
+            ast::IntType::U64 => {
+                self.code_context.add(CMP.op1(register::RAX).op2(0_u32));
+                self.trap(&JNE, "arithmetic overflow", span);
+            }
+            _ => {
+                let extend = extend_int(register::RCX, int_type.size(), int_type.is_signed())
+                    .expect("narrow integer type");
+                self.code_context.add_slice(&[
+                    MOV.op1(register::RCX).op2(register::RAX),
+                    extend,
+                    CMP.op1(register::RAX).op2(register::RCX),
+                ]);
+                self.trap(&JNE, "arithmetic overflow", span);
+            }
+        }
+    }
+
+    /// Panics with `message` at `span` when `jump` is taken
+    fn trap(&mut self, jump: &Mnemonic, message: &str, span: &ast::Span) {
+        let skip = JMP_REL.op1(Operand::Offset32(0)).as_vec().len();
+        self.code_context
+            .add(jump.op1(Operand::Offset32(skip as i32)));
+        self.code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let no_trap = self.code_context.get_pc() - 1;
+        stdlib::panic(&mut self.code_context, &span.to_string(), Some(message));
+        self.code_context.patch_jump(no_trap);
+    }
+
+    fn load_int_variable(&mut self, variable: &Variable, reg: register::Register) {
+        let int_type = variable
+            .value_type
+            .int_type()
+            .unwrap_or_else(|| panic!("Expected integer variable: {variable:#?}"));
+        let (size, signed) = (int_type.size(), int_type.is_signed());
+
+        match &variable.value_loc {
+            ValueLocation::Stack(stack_loc) => {
+                let value_loc: u32 = stack_loc.into();
+                if variable.reference {
+                    self.code_context.add_slice(&[
+                        MOV.op1(reg)
+                            .op2(register::RBP)
+                            .disp(Operand::Offset32(-(value_loc as i32))),
+                        load_int(reg, reg, 0, size, signed),
+                    ]);
+                } else {
+                    self.code_context.add(load_int(
+                        reg,
+                        register::RBP,
+                        -(value_loc as i32),
+                        size,
+                        signed,
+                    ));
+                }
+            }
+            ValueLocation::DataSection(_) => {
+                self.code_context.add_slice(&[
+                    MOV.op1(reg).op2(0_u64).symbol(variable.name.clone()),
+                    load_int(reg, reg, 0, size, signed),
+                ]);
+            }
+        }
     }
 
-    fn get_variable_mut(&mut self, scope: &str, id: &str) -> Option<&mut Variable> {
-        let id = format!("{}::{}", scope, id);
-        // dbg!(&scope, &id);
+    /// Puts the address of a variable into `reg`, strings point at their
+    /// length
+    fn load_address(&mut self, expr: &ast::Expression, reg: register::Register) {
+        let variable = self.expression_variable(expr);
+
+        match &variable.value_loc {
+            ValueLocation::Stack(stack_loc) => {
+                let value_loc: u32 = stack_loc.into();
+                if variable.reference {
+                    self.code_context.add(
+                        MOV.op1(reg)
+                            .op2(register::RBP)
+                            .disp(Operand::Offset32(-(value_loc as i32))),
+                    );
+                } else {
+                    self.code_context
+                        .add_slice(&[MOV.op1(reg).op2(register::RBP), SUB.op1(reg).op2(value_loc)]);
+                }
+            }
+            ValueLocation::DataSection(_) => {
+                self.code_context
+                    .add(MOV.op1(reg).op2(0_u64).symbol(variable.name.clone()));
+            }
+        }
+    }
 
-        self.variables.get_mut(&id)
+    /// Variable named by `expr` or by the reference `&expr`, string literals
+    /// are laid out as consts
+    fn expression_variable(&self, expr: &ast::Expression) -> Variable {
+        if let ast::Expression::Literal(ast::Literal::String(_), span) = expr {
+            let id = variables::string_literal(span);
+            return self
+                .variables
+                .get(&id)
+                .unwrap_or_else(|| panic!("undefined string literal: {id}"))
+                .clone();
+        }
+        let id = match expr {
+            ast::Expression::Ident(id) => id,
+            ast::Expression::Unary(ast::UnaryOperation::Ref(expr), _) => match expr.as_ref() {
+                ast::Expression::Ident(id) => id,
+                _ => panic!("Expected a variable, found: {expr:?}"),
+            },
+            _ => panic!("Expected a variable, found: {expr:?}"),
+        };
+        self.variable(id).clone()
+    }
+
+    /// Reads stdin, or the file descriptor given first, into a string
+    /// variable. The number of bytes read is left in RAX.
+    fn visit_read(&mut self, call: &ast::Call, scope: &str) {
+        let (fd, buffer, count) = match call.args.as_slice() {
+            [buffer, count] => (None, buffer, count),
+            [fd, buffer, count] => (Some(fd), buffer, count),
+            _ => panic!(
+                "Function read expects an optional file descriptor, a string and a byte count"
+            ),
+        };
+        let variable = self.expression_variable(buffer);
+        if matches!(variable.value_loc, ValueLocation::DataSection(_)) {
+            panic!("Cannot read into const data: {variable:#?}");
+        }
+
+        if let Some(fd) = fd {
+            self.visit_int_expression(fd, scope, ast::IntType::I64);
+            self.code_context
+                .add_slice(&self.stack_manager.push_register(register::RAX));
+        }
+        self.visit_int_expression(count, scope, ast::IntType::U64);
+        self.code_context
+            .add_slice(&self.stack_manager.push_register(register::RAX));
+        self.load_address(buffer, register::RSI);
+
+        // Never read past the end of the string, strings pointed to can only
+        // be overwritten up to their length
+        if variable.reference {
+            self.code_context.add(
+                MOV.op1(register::RCX)
+                    .op2(register::RSI)
+                    .disp(Operand::Offset32(0)),
+            );
+        } else {
+            let capacity = variable.value_size - mem::size_of::<u64>() - 1;
+            self.code_context
+                .add(MOV.op1(register::RCX).op2(capacity as u64));
+        }
+        self.code_context
+            .add_slice(&self.stack_manager.pop_register(register::RDX));
+        self.code_context
+            .add(CMP.op1(register::RDX).op2(register::RCX));
+        self.code_context.add(JB.op1(Operand::Offset32(0)));
+        let within_capacity = self.code_context.get_pc() - 1;
+        self.code_context
+            .add(MOV.op1(register::RDX).op2(register::RCX));
+        self.code_context.patch_jump(within_capacity);
+
+        match fd {
+            Some(_) => {
+                self.code_context
+                    .add_slice(&self.stack_manager.pop_register(register::RDI));
+                stdlib::read_fd(&mut self.code_context);
+            }
+            None => {
+                self.code_context
+                    .add(MOV.op1(register::RDI).op2(register::RSI));
+                stdlib::read(&mut self.code_context);
+            }
+        }
+    }
+
+    /// Calls the file builtins, their result or negative error code is left in
+    /// RAX
+    fn visit_file_call(&mut self, call: &ast::Call, scope: &str) {
+        let name = call.func_name.value.as_str();
+        match (name, call.args.as_slice()) {
+            ("open", [path, mode]) => {
+                let mode = self.const_string(mode);
+                self.visit_string_expression(path, scope);
+                stdlib::open(&mut self.code_context, &mode);
+            }
+            ("close", [fd]) => {
+                self.visit_int_expression(fd, scope, ast::IntType::I64);
+                self.code_context
+                    .add(MOV.op1(register::RDI).op2(register::RAX));
+                stdlib::close(&mut self.code_context);
+            }
+            ("write", [fd, string]) => {
+                self.visit_int_expression(fd, scope, ast::IntType::I64);
+                self.code_context
+                    .add_slice(&self.stack_manager.push_register(register::RAX));
+                self.visit_string_expression(string, scope);
+                self.code_context
+                    .add_slice(&self.stack_manager.push_register(register::RAX));
+                // The fd may be stdout, keep the output in order
+                stdlib::flush(&mut self.code_context);
+                self.code_context
+                    .add_slice(&self.stack_manager.pop_register(register::RSI));
+                self.code_context
+                    .add_slice(&self.stack_manager.pop_register(register::RDI));
+                stdlib::write(&mut self.code_context);
+            }
+            ("write_file", [path, string]) => {
+                self.visit_string_expression(path, scope);
+                self.code_context
+                    .add_slice(&self.stack_manager.push_register(register::RAX));
+                self.visit_string_expression(string, scope);
+                self.code_context
+                    .add(MOV.op1(register::RSI).op2(register::RAX));
+                self.code_context
+                    .add_slice(&self.stack_manager.pop_register(register::RAX));
+                stdlib::write_file(&mut self.code_context);
+            }
+            _ => panic!(
+                "Function {name} does not take {} arguments",
+                call.args.len()
+            ),
+        }
+    }
+
+    /// Value of a const string, known at compile time
+    fn const_string(&self, expr: &ast::Expression) -> String {
+        let variable = self.expression_variable(expr);
+        match (&variable.value_loc, &variable.value_type) {
+            (ValueLocation::DataSection(_), Value::String(s)) => s.clone(),
+            _ => panic!("Expected a const string, found: {variable:#?}"),
+        }
+    }
+
+    /// Evaluates a string expression and puts the address of the string into
+    /// RAX
+    fn visit_string_expression(&mut self, expr: &ast::Expression, scope: &str) {
+        match expr {
+            ast::Expression::Ident(_)
+            | ast::Expression::Unary(ast::UnaryOperation::Ref(_), _)
+            | ast::Expression::Literal(ast::Literal::String(_), _) => {
+                self.load_address(expr, register::RAX);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "arg" => {
+                let [index] = call.args.as_slice() else {
+                    panic!("Function arg expects one argument");
+                };
+                self.visit_int_expression(index, scope, ast::IntType::U64);
+                stdlib::arg(&mut self.code_context);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "read_line" => {
+                stdlib::read_line(&mut self.code_context);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "read_file" => {
+                let [path] = call.args.as_slice() else {
+                    panic!("Function read_file expects one argument");
+                };
+                self.visit_string_expression(path, scope);
+                stdlib::read_file(&mut self.code_context);
+            }
+            ast::Expression::Call(call) if call.func_name.value == "env" => {
+                let [name] = call.args.as_slice() else {
+                    panic!("Function env expects one argument");
+                };
+                self.visit_string_expression(name, scope);
+                stdlib::env(&mut self.code_context);
+            }
+            ast::Expression::Call(call) => self.visit_function_call(call, scope),
+            _ => panic!("Expected string expression, found: {expr:?}"),
+        }
+    }
+
+    /// Points a string variable at the string RAX points to
+    fn store_pointer(&mut self, variable: &Variable) {
+        let ValueLocation::Stack(stack_loc) = &variable.value_loc else {
+            panic!("Cannot assign to const data: {variable:#?}");
+        };
+        let value_loc: u32 = stack_loc.into();
+        self.code_context.add(store_int(
+            register::RBP,
+            register::RAX,
+            -(value_loc as i32),
+            mem::size_of::<u64>(),
+        ));
+    }
+
+    /// Writes a string into the variable slot, length first
+    fn store_string(&mut self, variable: &Variable, s: &str) {
+        let ValueLocation::Stack(stack_loc) = &variable.value_loc else {
+            panic!("Cannot assign to const data: {variable:#?}");
+        };
+        if variable.reference {
+            unreachable!("the type checker only assigns literals to inline strings");
+        }
+        let value_loc: u32 = stack_loc.into();
+
+        let chunks = str_to_u64(&format!("{s}\0")).into_iter().rev();
+        let words = std::iter::once(s.len() as u64).chain(chunks);
+        for (i, word) in words.enumerate() {
+            self.code_context.add_slice(&[
+                MOV.op1(register::RAX).op2(word),
+                store_int(
+                    register::RBP,
+                    register::RAX,
+                    (i * mem::size_of::<u64>()) as i32 - value_loc as i32,
+                    mem::size_of::<u64>(),
+                ),
+            ]);
+        }
+    }
+
+    /// Stores RAX into the variable at the variable's own size
+    fn store_int_variable(&mut self, variable: &Variable) {
+        let int_type = variable
+            .value_type
+            .int_type()
+            .unwrap_or_else(|| panic!("Expected integer variable: {variable:#?}"));
+
+        match &variable.value_loc {
+            ValueLocation::Stack(stack_loc) => {
+                let value_loc: u32 = stack_loc.into();
+                if variable.reference {
+                    self.code_context.add_slice(&[
+                        MOV.op1(register::RCX)
+                            .op2(register::RBP)
+                            .disp(Operand::Offset32(-(value_loc as i32))),
+                        store_int(register::RCX, register::RAX, 0, int_type.size()),
+                    ]);
+                } else {
+                    self.code_context.add(store_int(
+                        register::RBP,
+                        register::RAX,
+                        -(value_loc as i32),
+                        int_type.size(),
+                    ));
+                }
+            }
+            ValueLocation::DataSection(_) => panic!("Cannot assign to const data: {variable:#?}"),
+        }
+    }
+
+    fn print_int(&mut self, int_type: ast::IntType, stream: stdlib::Stream) {
+        self.code_context
+            .add_slice(&self.stack_manager.push_register(abi::ARG_REGISTERS[0]));
+        self.code_context
+            .add(MOV.op1(abi::ARG_REGISTERS[0]).op2(register::RAX));
+        self.code_context
+            .add_slice(&self.stack_manager.align_for_call());
+
+        if int_type.is_signed() {
+            stdlib::printi(&mut self.code_context, stream);
+        } else {
+            stdlib::printd(&mut self.code_context, stream);
+        }
+
+        self.code_context
+            .add_slice(&self.stack_manager.unalign_after_call());
+        self.code_context
+            .add_slice(&self.stack_manager.pop_register(abi::ARG_REGISTERS[0]));
+    }
+
+    fn print_formatted_int(&mut self, format: stdlib::IntFormat, stream: stdlib::Stream) {
+        self.code_context
+            .add_slice(&self.stack_manager.push_register(abi::ARG_REGISTERS[0]));
+        self.code_context
+            .add(MOV.op1(abi::ARG_REGISTERS[0]).op2(register::RAX));
+        self.code_context
+            .add_slice(&self.stack_manager.align_for_call());
+
+        stdlib::print_formatted_int(&mut self.code_context, stream, format);
+
+        self.code_context
+            .add_slice(&self.stack_manager.unalign_after_call());
+        self.code_context
+            .add_slice(&self.stack_manager.pop_register(abi::ARG_REGISTERS[0]));
+    }
+
+    /// Prints the string RAX points to
+    fn print_string(&mut self, stream: stdlib::Stream) {
+        self.code_context
+            .add_slice(&self.stack_manager.push_register(abi::ARG_REGISTERS[0]));
+        self.code_context
+            .add(MOV.op1(abi::ARG_REGISTERS[0]).op2(register::RAX));
+        self.code_context
+            .add_slice(&self.stack_manager.align_for_call());
+
+        stdlib::print(&mut self.code_context, stream);
+
+        self.code_context
+            .add_slice(&self.stack_manager.unalign_after_call());
+        self.code_context
+            .add_slice(&self.stack_manager.pop_register(abi::ARG_REGISTERS[0]));
+    }
+
+    fn print_char(&mut self, stream: stdlib::Stream) {
+        self.code_context
+            .add_slice(&self.stack_manager.push_register(abi::ARG_REGISTERS[0]));
+        self.code_context
+            .add(MOV.op1(abi::ARG_REGISTERS[0]).op2(register::RAX));
+        self.code_context
+            .add_slice(&self.stack_manager.align_for_call());
+
+        stdlib::print_char(&mut self.code_context, stream);
+
+        self.code_context
+            .add_slice(&self.stack_manager.unalign_after_call());
+        self.code_context
+            .add_slice(&self.stack_manager.pop_register(abi::ARG_REGISTERS[0]));
+    }
+
+    fn int_type_of(&self, expr: &ast::Expression) -> Option<ast::IntType> {
+        self.types.int_type(expr)
+    }
+
+    fn is_char_expression(&self, expr: &ast::Expression) -> bool {
+        self.types.get(expr) == &ast::Type::new(ast::TypeName::Char, vec![])
+    }
+
+    // References to strings are strings too
+    fn is_string_expression(&self, expr: &ast::Expression) -> bool {
+        self.types.get(expr).name == ast::TypeName::String
+    }
+
+    /// Variable the identifier is bound to
+    fn variable(&self, id: &ast::Ident) -> &Variable {
+        let path = &self.bindings.get(id).path;
+        self.variables
+            .get(path)
+            .unwrap_or_else(|| panic!("undefined variable: {path}"))
     }
 
     fn call(&mut self, label: &str) -> Vec<Mnemonic> {
@@ -404,6 +1289,50 @@ impl TextBuilder {
     }
 }
 
+fn binary_operation(binary: &ast::BinaryOperation, int_type: ast::IntType) -> Vec<Mnemonic> {
+    use ast::BinaryOperation::*;
+
+    let signed = int_type.is_signed();
+    let division = |remainder: bool| {
+        let mut code = if signed {
+            vec![CQO.no_op(), IDIV.op1(register::RCX)]
+        } else {
+            vec![
+                XOR.op1(register::RDX).op2(register::RDX),
+                DIV.op1(register::RCX),
+            ]
+        };
+        if remainder {
+            code.push(MOV.op1(register::RAX).op2(register::RDX));
+        }
+        code
+    };
+
+    let set = match (binary, signed) {
+        (Plus(..), _) => return vec![ADD.op1(register::RAX).op2(register::RCX)],
+        (Minus(..), _) => return vec![SUB.op1(register::RAX).op2(register::RCX)],
+        (Mul(..), _) => return vec![IMUL.op1(register::RAX).op2(register::RCX)],
+        (Div(..), _) => return division(false),
+        (Rem(..), _) => return division(true),
+        (Equal(..), _) => SETE.clone(),
+        (NotEqual(..), _) => SETNE.clone(),
+        (Less(..), true) => SETL.clone(),
+        (LessEqual(..), true) => SETLE.clone(),
+        (Greater(..), true) => SETG.clone(),
+        (GreaterEqual(..), true) => SETGE.clone(),
+        (Less(..), false) => SETB.clone(),
+        (LessEqual(..), false) => SETBE.clone(),
+        (Greater(..), false) => SETA.clone(),
+        (GreaterEqual(..), false) => SETAE.clone(),
+    };
+    vec![
+        CMP.op1(register::RAX).op2(register::RCX),
+        // MOV leaves the flags untouched
+        MOV.op1(register::RAX).op2(0_u64),
+        set.op1(register::AL),
+    ]
+}
+
 fn str_to_u64(s: &str) -> Vec<u64> {
     s.as_bytes()
         .chunks(8)
@@ -1,14 +1,34 @@
 use crate::emitter::stack::StackManager;
-use crate::emitter::variables::ValueLocation;
-use crate::emitter::variables::Variable;
 
 use super::super::{code_context::CodeContext, mnemonics::*};
 
+pub const STDIN_FD: u64 = 0x0;
 pub const STDOUT_FD: u64 = 0x1;
+pub const STDERR_FD: u64 = 0x2;
 
+pub const SYS_READ: u64 = 0x0;
 pub const SYS_WRITE: u64 = 0x1;
+pub const SYS_OPEN: u64 = 0x2;
+pub const SYS_CLOSE: u64 = 0x3;
+pub const SYS_MMAP: u64 = 0x9;
+pub const SYS_IOCTL: u64 = 0x10;
 pub const SYS_EXIT: u64 = 0x3c;
 
+pub const O_RDONLY: u64 = 0o0;
+pub const O_WRONLY: u64 = 0o1;
+pub const O_RDWR: u64 = 0o2;
+pub const O_CREAT: u64 = 0o100;
+pub const O_TRUNC: u64 = 0o1000;
+pub const O_APPEND: u64 = 0o2000;
+/// rw-r--r--
+pub const FILE_PERMISSIONS: u64 = 0o644;
+
+pub const PROT_READ_WRITE: u64 = 0x3;
+pub const MAP_PRIVATE_ANONYMOUS: u64 = 0x22;
+
+/// Fails on file descriptors that are not terminals
+pub const TCGETS: u64 = 0x5401;
+
 pub const ARG_REGISTERS: &[register::Register] = &[
     register::RDI,
     register::RSI,
@@ -18,39 +38,6 @@ pub const ARG_REGISTERS: &[register::Register] = &[
     register::R9,
 ];
 
-pub fn push_args(code_context: &mut CodeContext, stack: &mut StackManager, args: &[Variable]) {
-    args.iter().enumerate().for_each(|(i, _)| {
-        code_context.add_slice(&stack.push_register(ARG_REGISTERS[i]));
-    });
-
-    args.iter().enumerate().for_each(|(i, arg)| {
-        match &arg.value_loc {
-            ValueLocation::Stack(stack_loc) => {
-                dbg!(&arg);
-                let data_loc: u32 = stack_loc.into();
-                code_context.add_slice(&[
-                    MOV.op1(ARG_REGISTERS[i]).op2(register::RBP),
-                    SUB.op1(ARG_REGISTERS[i]).op2(data_loc),
-                ]);
-            }
-            ValueLocation::DataSection(_) => {
-                code_context.add(
-                    MOV.op1(ARG_REGISTERS[i])
-                        .op2(0_u64)
-                        .symbol(arg.name.clone()),
-                );
-            }
-        }
-        if !arg.reference {
-            code_context.add(
-                MOV.op1(ARG_REGISTERS[i])
-                    .op2(ARG_REGISTERS[i])
-                    .disp(Operand::Offset32(0)),
-            );
-        }
-    });
-}
-
 pub fn pop_args(code_context: &mut CodeContext, stack: &mut StackManager, args_count: usize) {
     (0..args_count).rev().for_each(|i| {
         code_context.add_slice(&stack.pop_register(ARG_REGISTERS[i]));
@@ -23,6 +23,8 @@ pub struct CodeContext {
     offsets: Vec<usize>,
     calls: BTreeMap<String, Call>,
     labels: BTreeMap<String, usize>,
+    // Runtime routines called so far, by label
+    routines: BTreeMap<String, fn(&mut CodeContext)>,
     symbols: BTreeMap<String, Symbol>,
     relocations: Vec<Relocation>,
     image_base: u64,
@@ -36,6 +38,7 @@ impl CodeContext {
             offsets: vec![0],
             calls: BTreeMap::new(),
             labels: BTreeMap::new(),
+            routines: BTreeMap::new(),
             symbols: BTreeMap::new(),
             relocations: vec![],
             image_base,
@@ -65,10 +68,54 @@ impl CodeContext {
         }
     }
 
+    pub fn append(&mut self, other: &CodeContext) {
+        self.add_slice(&other.instructions);
+    }
+
+    /// Points the relative jump at instruction `i` to the current end of code
+    pub fn patch_jump(&mut self, i: usize) {
+        let target = self.get_code_size() - self.get_offset(i + 1);
+        self.get_mut(i)
+            .set_op1(mnemonics::Operand::Offset32(target as i32));
+    }
+
+    /// Adds the relative `jump` to the earlier code offset `target`
+    pub fn jump_back(&mut self, jump: &Mnemonic, target: usize) {
+        let size = jump.op1(mnemonics::Operand::Offset32(0)).as_vec().len();
+        let distance = self.get_code_size() + size - target;
+        self.add(jump.op1(mnemonics::Operand::Offset32(-(distance as i32))));
+    }
+
     pub fn set_label(&mut self, label: String) {
         self.labels.insert(label, self.get_code_size());
     }
 
+    /// Calls the runtime routine `label`, its `body` is emitted once by
+    /// `add_routines`
+    pub fn call_routine(&mut self, label: &str, body: fn(&mut CodeContext)) {
+        self.routines.entry(label.to_string()).or_insert(body);
+        self.add(
+            mnemonics::CALL
+                .op1(mnemonics::Operand::Offset32(0))
+                .symbol(label.to_string()),
+        );
+    }
+
+    /// Emits the routines called so far, and the ones they call, as labelled
+    /// functions
+    pub fn add_routines(&mut self) {
+        while let Some((label, body)) = self
+            .routines
+            .iter()
+            .find(|(label, _)| !self.labels.contains_key(*label))
+            .map(|(label, body)| (label.clone(), *body))
+        {
+            self.set_label(label);
+            body(self);
+            self.add(mnemonics::RET.no_op());
+        }
+    }
+
     pub fn get_pc(&self) -> usize {
         self.pc
     }
@@ -294,6 +341,30 @@ mod tests {
         assert_eq_hex(code.to_bin(), expected);
     }
 
+    #[rstest]
+    fn test_routines() {
+        let mut code = CodeContext::new(0);
+        code.call_routine("#outer", |code| {
+            code.call_routine("#inner", |code| {
+                code.add(INC.op1(register::RAX));
+            });
+            code.call_routine("#inner", |code| {
+                code.add(INC.op1(register::RAX));
+            });
+        });
+        code.call_routine("#outer", |_| ());
+        code.add_routines();
+
+        let call_size = CALL.op1(Offset32(0)).as_vec().len();
+        let inc_size = INC.op1(register::RAX).as_vec().len();
+        let labels = code.get_labels();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels["#outer"], 2 * call_size);
+        // The return of outer follows its two calls
+        assert_eq!(labels["#inner"], 4 * call_size + 1);
+        assert_eq!(code.get_code_size(), 4 * call_size + 1 + inc_size + 1);
+    }
+
     // #[rstest]
     // fn test_jmp() {
     //     let mut code = CodeContext::new(0);
@@ -0,0 +1,79 @@
+use std::mem;
+
+use super::stdlib::IntFormat;
+
+/// Part of a format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    Text(String),
+    /// Placeholder for the next argument, integers may come with a layout
+    Arg(Option<IntFormat>),
+}
+
+/// Splits a format string such as "{} = {:08x}" into text and placeholders.
+/// `{{` and `}}` stand for literal braces.
+pub fn parse(format: &str) -> Vec<Piece> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                text.push(c);
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => panic!("Unterminated placeholder in format string: {format:?}"),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(mem::take(&mut text)));
+                }
+                pieces.push(Piece::Arg(int_format(&spec, format)));
+            }
+            '}' => panic!("Unmatched }} in format string: {format:?}"),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+/// Parses the `:[<|>][0][width][x]` layout of a placeholder
+fn int_format(spec: &str, format: &str) -> Option<IntFormat> {
+    if spec.is_empty() {
+        return None;
+    }
+    let Some(mut spec) = spec.strip_prefix(':') else {
+        panic!("Invalid placeholder {{{spec}}} in format string: {format:?}");
+    };
+
+    let mut int_format = IntFormat::default();
+    if let Some(rest) = spec.strip_prefix('<') {
+        int_format.left_align = true;
+        spec = rest;
+    } else if let Some(rest) = spec.strip_prefix('>') {
+        spec = rest;
+    }
+    if let Some(rest) = spec.strip_prefix('0') {
+        int_format.zero_pad = true;
+        spec = rest;
+    }
+    if let Some(rest) = spec.strip_suffix('x') {
+        int_format.hex = true;
+        spec = rest;
+    }
+    if !spec.is_empty() {
+        int_format.width = spec
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid width {spec:?} in format string: {format:?}"));
+    }
+    Some(int_format)
+}
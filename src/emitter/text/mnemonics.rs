@@ -6,7 +6,7 @@ use crate::emitter::symbols::{self, DataSymbol, SymbolType};
 use self::register::RegisterSize;
 
 const REGISTER_EXT_INDEX: u8 = 0x30;
-const JMP_PREFIX: u8 = 0x0F;
+const OPCODE_ESCAPE: u8 = 0x0F;
 
 const REX_WRITE: u8 = 0b01001000;
 const REX_READ: u8 = 0b01000100;
@@ -14,6 +14,7 @@ const REX_X: u8 = 0b01000010;
 const REX_B: u8 = 0b01000001;
 
 const IMM16_PREFIX: u8 = 0x66;
+const SIB_RSP_BASE: u8 = 0x24;
 
 const MOD_ADDRESS: u8 = 0b00;
 const MOD_DISP8: u8 = 0b01;
@@ -27,6 +28,7 @@ pub mod register {
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum RegisterSize {
+        B,
         W,
         D,
         Q,
@@ -43,6 +45,23 @@ pub mod register {
         const fn new(code: u8, size: RegisterSize, ext: bool) -> Self {
             Self { code, size, ext }
         }
+
+        /// Same register accessed with another width, e.g. `RAX` -> `AL`.
+        pub const fn with_size(self, size: RegisterSize) -> Self {
+            Self::new(self.code, size, self.ext)
+        }
+    }
+
+    impl RegisterSize {
+        pub fn from_bytes(size: usize) -> Self {
+            match size {
+                1 => RegisterSize::B,
+                2 => RegisterSize::W,
+                4 => RegisterSize::D,
+                8 => RegisterSize::Q,
+                _ => panic!("invalid register size: {size}"),
+            }
+        }
     }
 
     macro_rules! registers {
@@ -82,9 +101,17 @@ pub mod register {
         R8 = (0x0, ext),
         R9 = (0x1, ext),
         R10 = (0x2, ext),
-        R11 = (0x3, ext)
+        R11 = (0x3, ext),
+        R12 = (0x4, ext),
+        R13 = (0x5, ext)
     );
 
+    // Only the low byte registers that do not need a REX prefix
+    pub const AL: Register = Register::new(0x0, RegisterSize::B, false);
+    pub const CL: Register = Register::new(0x1, RegisterSize::B, false);
+    pub const DL: Register = Register::new(0x2, RegisterSize::B, false);
+    pub const BL: Register = Register::new(0x3, RegisterSize::B, false);
+
     // const AX: Register = Register::new(0x0, RegisterSize::W, false);
     // const EAX: Register = Register::new(0x0, RegisterSize::D, false);
     // const RAX: Register = Register::new(0x0, RegisterSize::Q, false);
@@ -205,7 +232,7 @@ pub struct SymbolMetadata {
 pub struct Mnemonic {
     name: MnemonicName,
     has_rex_w: bool,
-    has_jump_prefix: bool,
+    has_escape_prefix: bool,
     reg: u8,
     rm: u8,
     opcodes: HashMap<OperandEncoding, u8>,
@@ -255,7 +282,7 @@ impl Mnemonic {
         Mnemonic {
             name,
             has_rex_w: true,
-            has_jump_prefix: false,
+            has_escape_prefix: false,
             opcodes: HashMap::new(),
             reg: 0,
             rm: 0,
@@ -302,9 +329,10 @@ impl Mnemonic {
         cloned
     }
 
-    pub fn has_jump_prefix(&self) -> Self {
+    /// Two-byte opcode, preceded by 0x0F
+    pub fn escape_prefix(&self) -> Self {
         let mut cloned = self.clone();
-        cloned.has_jump_prefix = true;
+        cloned.has_escape_prefix = true;
         cloned
     }
 
@@ -370,56 +398,75 @@ impl Mnemonic {
 
     pub fn as_vec(&mut self) -> Vec<u8> {
         if self.no_op {
-            return vec![*self.opcodes.get(&OperandEncoding::I).unwrap()];
+            let opcode = *self.opcodes.get(&OperandEncoding::I).unwrap();
+            if self.has_rex_w {
+                return vec![REX_WRITE, opcode];
+            }
+            return vec![opcode];
         }
         let mut result = vec![];
         let mut operand_enc;
 
-        let mut prefix = 0;
+        let mut size_prefix = false;
+        let mut rex = 0;
         let mut _mod = 0;
         let mut reg = self.reg;
         let mut rm = self.rm;
+        let mut needs_sib = false;
 
         match self.op1 {
             Operand::Register(dst) => {
-                match dst.size {
-                    RegisterSize::W => prefix |= IMM16_PREFIX,
-                    RegisterSize::D => (),
-                    RegisterSize::Q => {
-                        if self.has_rex_w {
-                            prefix |= REX_WRITE;
-                        }
-                    }
-                }
+                let mut operand_size = dst.size;
                 _mod = MOD_REG;
                 rm = dst.code;
 
                 if dst.ext {
-                    prefix |= REX_B;
+                    rex |= REX_B;
                 }
 
                 match self.op2 {
                     Operand::Register(src) => {
-                        assert!(
-                            src.size == dst.size,
-                            "Register sizes do not match: {:#?}, {:#?}",
-                            src,
-                            dst
-                        );
-                        operand_enc = Some(OperandEncoding::RM);
-
-                        _mod = match self.disp {
-                            Operand::Offset8(_) => MOD_DISP8,
-                            Operand::Offset32(_) => MOD_DISP32,
-                            _ => {
-                                operand_enc = Some(OperandEncoding::MR);
-                                MOD_REG
-                            }
+                        let memory_mod = match self.disp {
+                            Operand::Offset8(_) => Some(MOD_DISP8),
+                            Operand::Offset32(_) => Some(MOD_DISP32),
+                            _ => None,
                         };
-
-                        reg = src.code;
-                        if src.ext {
-                            prefix |= REX_READ;
+                        let has_mr = self.opcodes.contains_key(&OperandEncoding::MR);
+                        let has_rm = self.opcodes.contains_key(&OperandEncoding::RM);
+
+                        if has_mr && (memory_mod.is_none() || !has_rm) {
+                            // op1 is r/m (a register or a memory base), op2 is reg
+                            operand_enc = Some(OperandEncoding::MR);
+                            if let Some(memory_mod) = memory_mod {
+                                _mod = memory_mod;
+                                operand_size = src.size;
+                                needs_sib = dst.code == register::SP.code;
+                            } else {
+                                assert!(
+                                    src.size == dst.size,
+                                    "Register sizes do not match: {:#?}, {:#?}",
+                                    src,
+                                    dst
+                                );
+                            }
+                            reg = src.code;
+                            if src.ext {
+                                rex |= REX_READ;
+                            }
+                        } else {
+                            // op1 is reg, op2 is r/m (a register or a memory base)
+                            operand_enc = Some(OperandEncoding::RM);
+                            _mod = memory_mod.unwrap_or(MOD_REG);
+                            needs_sib = memory_mod.is_some() && src.code == register::SP.code;
+                            reg = dst.code;
+                            rm = src.code;
+                            rex = 0;
+                            if dst.ext {
+                                rex |= REX_READ;
+                            }
+                            if src.ext {
+                                rex |= REX_B;
+                            }
                         }
                     }
                     _ => {
@@ -433,10 +480,20 @@ impl Mnemonic {
                         }
                     }
                 }
+
+                match operand_size {
+                    RegisterSize::B | RegisterSize::D => (),
+                    RegisterSize::W => size_prefix = true,
+                    RegisterSize::Q => {
+                        if self.has_rex_w {
+                            rex |= REX_WRITE;
+                        }
+                    }
+                }
             }
             Operand::Imm8(_) => operand_enc = Some(OperandEncoding::I),
             Operand::Imm16(_) => {
-                prefix |= IMM16_PREFIX;
+                size_prefix = true;
                 operand_enc = Some(OperandEncoding::I)
             }
             Operand::Imm32(_) => operand_enc = Some(OperandEncoding::I),
@@ -444,11 +501,14 @@ impl Mnemonic {
             Operand::Offset32(_) => operand_enc = Some(OperandEncoding::D),
             _ => unreachable!("Invalid first operand: {:#?}", self.op1),
         }
-        if prefix != 0 {
-            result.extend(prefix.to_le_bytes());
+        if size_prefix {
+            result.extend(IMM16_PREFIX.to_le_bytes());
+        }
+        if rex != 0 {
+            result.extend(rex.to_le_bytes());
         }
-        if self.has_jump_prefix {
-            result.extend(JMP_PREFIX.to_le_bytes());
+        if self.has_escape_prefix {
+            result.extend(OPCODE_ESCAPE.to_le_bytes());
         }
 
         // dbg!(operand_enc);
@@ -463,12 +523,11 @@ impl Mnemonic {
         // println!("{:0b}", mod_rm);
 
         match operand_enc.unwrap() {
-            OperandEncoding::MR => {
-                result.extend(mod_rm.to_le_bytes());
-            }
-            OperandEncoding::RM => {
-                let mod_rm = _mod << 6 | rm << 3 | reg;
+            OperandEncoding::MR | OperandEncoding::RM => {
                 result.extend(mod_rm.to_le_bytes());
+                if needs_sib {
+                    result.extend(SIB_RSP_BASE.to_le_bytes());
+                }
                 self.value_loc = result.len();
                 match self.disp {
                     Operand::Offset8(_) | Operand::Offset32(_) => result.extend(self.disp.as_vec()),
@@ -541,27 +600,39 @@ impl Mnemonic {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MnemonicName {
     Mov,
+    Movsx,
+    Movzx,
     Add,
     Sub,
     Mul,
+    Imul,
     Div,
+    Idiv,
+    Neg,
+    Cqo,
     Inc,
     Xor,
     And,
     Or,
     Shl,
+    Shr,
     Push,
     Pop,
     Call,
     Ret,
     SysCall,
     Cmp,
+    Set,
     Jmp,
     Jl,
     Jle,
     Jg,
     Jge,
     Je,
+    Jne,
+    Jb,
+    Jae,
+    Jo,
     Jz,
 }
 
@@ -573,6 +644,25 @@ lazy_static! {
     pub static ref MOV_MI: Mnemonic = Mnemonic::new(MnemonicName::Mov)
         .opcode(0xC7, OperandEncoding::MI)
         .reg(0);
+    pub static ref MOV_MR: Mnemonic =
+        Mnemonic::new(MnemonicName::Mov).opcode(0x89, OperandEncoding::MR);
+    pub static ref MOV8_MR: Mnemonic = Mnemonic::new(MnemonicName::Mov)
+        .opcode(0x88, OperandEncoding::MR)
+        .no_rex_w();
+    pub static ref MOVSX8: Mnemonic = Mnemonic::new(MnemonicName::Movsx)
+        .opcode(0xBE, OperandEncoding::RM)
+        .escape_prefix();
+    pub static ref MOVSX16: Mnemonic = Mnemonic::new(MnemonicName::Movsx)
+        .opcode(0xBF, OperandEncoding::RM)
+        .escape_prefix();
+    pub static ref MOVSXD: Mnemonic =
+        Mnemonic::new(MnemonicName::Movsx).opcode(0x63, OperandEncoding::RM);
+    pub static ref MOVZX8: Mnemonic = Mnemonic::new(MnemonicName::Movzx)
+        .opcode(0xB6, OperandEncoding::RM)
+        .escape_prefix();
+    pub static ref MOVZX16: Mnemonic = Mnemonic::new(MnemonicName::Movzx)
+        .opcode(0xB7, OperandEncoding::RM)
+        .escape_prefix();
     pub static ref ADD: Mnemonic = Mnemonic::new(MnemonicName::Add)
         .opcode(0x01, OperandEncoding::MR)
         .opcode(0x81, OperandEncoding::MI);
@@ -583,9 +673,20 @@ lazy_static! {
     pub static ref MUL: Mnemonic = Mnemonic::new(MnemonicName::Mul)
         .opcode(0xF7, OperandEncoding::M)
         .reg(4);
+    pub static ref IMUL: Mnemonic = Mnemonic::new(MnemonicName::Imul)
+        .opcode(0xAF, OperandEncoding::RM)
+        .escape_prefix();
     pub static ref DIV: Mnemonic = Mnemonic::new(MnemonicName::Div)
         .opcode(0xF7, OperandEncoding::M)
         .reg(6);
+    pub static ref IDIV: Mnemonic = Mnemonic::new(MnemonicName::Idiv)
+        .opcode(0xF7, OperandEncoding::M)
+        .reg(7);
+    pub static ref NEG: Mnemonic = Mnemonic::new(MnemonicName::Neg)
+        .opcode(0xF7, OperandEncoding::M)
+        .reg(3);
+    pub static ref CQO: Mnemonic =
+        Mnemonic::new(MnemonicName::Cqo).opcode(0x99, OperandEncoding::I);
     pub static ref INC: Mnemonic = Mnemonic::new(MnemonicName::Inc)
         .opcode(0xFF, OperandEncoding::M)
         .reg(0);
@@ -604,6 +705,9 @@ lazy_static! {
     pub static ref SHL: Mnemonic = Mnemonic::new(MnemonicName::Shl)
         .opcode(0xC1, OperandEncoding::MI)
         .reg(4);
+    pub static ref SHR: Mnemonic = Mnemonic::new(MnemonicName::Shr)
+        .opcode(0xC1, OperandEncoding::MI)
+        .reg(5);
     pub static ref SHL_CL: Mnemonic = Mnemonic::new(MnemonicName::Shl)
         .opcode(0xD3, OperandEncoding::M)
         .reg(4);
@@ -624,6 +728,8 @@ lazy_static! {
         .opcode(0x0F, OperandEncoding::I)
         .no_rex_w();
     pub static ref CMP: Mnemonic = Mnemonic::new(MnemonicName::Cmp)
+        .opcode(0x39, OperandEncoding::MR)
+        .opcode(0x3B, OperandEncoding::RM)
         .opcode(0x81, OperandEncoding::MI)
         .reg(7);
     pub static ref JMP: Mnemonic = Mnemonic::new(MnemonicName::Jmp)
@@ -631,32 +737,151 @@ lazy_static! {
         .reg(4)
         .rm(RM_DISP32)
         .no_rex_w();
+    pub static ref JMP_REL: Mnemonic = Mnemonic::new(MnemonicName::Jmp)
+        .opcode(0xE9, OperandEncoding::D)
+        .no_rex_w();
+    pub static ref JE: Mnemonic = Mnemonic::new(MnemonicName::Je)
+        .opcode(0x84, OperandEncoding::D)
+        .rm(RM_DISP32)
+        .no_rex_w()
+        .escape_prefix();
+    pub static ref JNE: Mnemonic = Mnemonic::new(MnemonicName::Jne)
+        .opcode(0x85, OperandEncoding::D)
+        .rm(RM_DISP32)
+        .no_rex_w()
+        .escape_prefix();
+    pub static ref JB: Mnemonic = Mnemonic::new(MnemonicName::Jb)
+        .opcode(0x82, OperandEncoding::D)
+        .rm(RM_DISP32)
+        .no_rex_w()
+        .escape_prefix();
+    pub static ref JO: Mnemonic = Mnemonic::new(MnemonicName::Jo)
+        .opcode(0x80, OperandEncoding::D)
+        .rm(RM_DISP32)
+        .no_rex_w()
+        .escape_prefix();
+    pub static ref JAE: Mnemonic = Mnemonic::new(MnemonicName::Jae)
+        .opcode(0x83, OperandEncoding::D)
+        .rm(RM_DISP32)
+        .no_rex_w()
+        .escape_prefix();
     pub static ref JLE: Mnemonic = Mnemonic::new(MnemonicName::Jle)
         .opcode(0x8E, OperandEncoding::D)
         .rm(RM_DISP32)
         .no_rex_w()
-        .has_jump_prefix();
+        .escape_prefix();
     pub static ref JL: Mnemonic = Mnemonic::new(MnemonicName::Jl)
         .opcode(0x8C, OperandEncoding::D)
         .rm(RM_DISP32)
         .no_rex_w()
-        .has_jump_prefix();
+        .escape_prefix();
     pub static ref JGE: Mnemonic = Mnemonic::new(MnemonicName::Jge)
         .opcode(0x8D, OperandEncoding::D)
         .rm(RM_DISP32)
         .no_rex_w()
-        .has_jump_prefix();
+        .escape_prefix();
     pub static ref JG: Mnemonic = Mnemonic::new(MnemonicName::Jg)
         .opcode(0x8F, OperandEncoding::D)
         .rm(RM_DISP32)
         .no_rex_w()
-        .has_jump_prefix();
+        .escape_prefix();
+}
+
+macro_rules! set_cc {
+    ( $($name:ident = $opcode:literal),* ) => {
+        lazy_static! {
+            $(
+                pub static ref $name: Mnemonic = Mnemonic::new(MnemonicName::Set)
+                    .opcode($opcode, OperandEncoding::M)
+                    .no_rex_w()
+                    .escape_prefix();
+            )*
+        }
+    };
 }
 
+set_cc!(
+    SETE = 0x94,
+    SETNE = 0x95,
+    SETB = 0x92,
+    SETAE = 0x93,
+    SETBE = 0x96,
+    SETA = 0x97,
+    SETL = 0x9C,
+    SETGE = 0x9D,
+    SETLE = 0x9E,
+    SETG = 0x9F
+);
+
 lazy_static! {
     pub static ref SIZE_OF_JMP: usize = JMP.op1(Operand::Imm32(0)).as_vec().len();
 }
 
+/// Loads an integer of `size` bytes from `[base + disp]` into the 64-bit `dst`,
+/// sign or zero extending it to the full register
+pub fn load_int(
+    dst: register::Register,
+    base: register::Register,
+    disp: i32,
+    size: usize,
+    signed: bool,
+) -> Mnemonic {
+    let mnemonic = match (size, signed) {
+        (1, true) => MOVSX8.clone(),
+        (1, false) => MOVZX8.clone(),
+        (2, true) => MOVSX16.clone(),
+        (2, false) => MOVZX16.clone(),
+        (4, true) => MOVSXD.clone(),
+        // writing a 32-bit register clears the upper half
+        (4, false) => {
+            return MOV
+                .op1(dst.with_size(RegisterSize::D))
+                .op2(base)
+                .disp(Operand::Offset32(disp))
+        }
+        (8, _) => MOV.clone(),
+        _ => panic!("invalid integer size: {size}"),
+    };
+    mnemonic.op1(dst).op2(base).disp(Operand::Offset32(disp))
+}
+
+/// Stores the low `size` bytes of `src` to `[base + disp]`
+pub fn store_int(
+    base: register::Register,
+    src: register::Register,
+    disp: i32,
+    size: usize,
+) -> Mnemonic {
+    let src = src.with_size(RegisterSize::from_bytes(size));
+    let mnemonic = match size {
+        1 => MOV8_MR.clone(),
+        _ => MOV_MR.clone(),
+    };
+    mnemonic.op1(base).op2(src).disp(Operand::Offset32(disp))
+}
+
+/// Truncates the 64-bit `reg` to its low `size` bytes and extends it back
+pub fn extend_int(reg: register::Register, size: usize, signed: bool) -> Option<Mnemonic> {
+    let mnemonic = match (size, signed) {
+        (1, true) => MOVSX8.clone(),
+        (1, false) => MOVZX8.clone(),
+        (2, true) => MOVSX16.clone(),
+        (2, false) => MOVZX16.clone(),
+        (4, true) => MOVSXD.clone(),
+        (4, false) => {
+            let reg = reg.with_size(RegisterSize::D);
+            return Some(MOV.op1(reg).op2(reg));
+        }
+        (8, _) => return None,
+        _ => panic!("invalid integer size: {size}"),
+    };
+    Some(
+        mnemonic
+            .op1(reg)
+            .op2(reg.with_size(RegisterSize::from_bytes(size))),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use crate::emitter::text::mnemonics::*;
@@ -714,6 +939,7 @@ mod tests {
     #[case::imm16(0xABCD_u16, vec ! [0x66, 0x68, 0xCD, 0xAB])]
     #[case::imm32(0xABCDEF12_u32, vec ! [0x68, 0x12, 0xEF, 0xCD, 0xAB])]
     #[case::Rcx(register::RCX, vec ! [0x51])]
+    #[case::R12(register::R12, vec ! [0x41, 0x54])]
     fn test_push(#[case] op1: impl Into<Operand>, #[case] expected: Vec<u8>) {
         let mut instruction = PUSH.op1(op1);
         assert_eq!(instruction.as_vec(), expected);
@@ -753,8 +979,9 @@ mod tests {
         assert_eq!(instruction.as_vec(), expected);
     }
 
-     #[rstest]
+    #[rstest]
     #[case::RcxRax(register::R11, register::R11, vec ! [0x4D, 0x31, 0xDB])]
+    #[case::R13R13(register::R13, register::R13, vec ! [0x4D, 0x31, 0xED])]
     fn test_xor(
         #[case] op1: impl Into<Operand>,
         #[case] op2: impl Into<Operand>,
@@ -835,6 +1062,123 @@ mod tests {
         assert_eq!(instruction.as_vec(), expected);
     }
 
+    #[rstest]
+    #[case::Byte(MOV8_MR.clone(), register::AL, vec ! [0x88, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Word(MOV_MR.clone(), register::AX, vec ! [0x66, 0x89, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Dword(MOV_MR.clone(), register::EAX, vec ! [0x89, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Qword(MOV_MR.clone(), register::RAX, vec ! [0x48, 0x89, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::R8(MOV_MR.clone(), register::R8, vec ! [0x4C, 0x89, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    fn test_mov_store(
+        #[case] mnemonic: Mnemonic,
+        #[case] src: register::Register,
+        #[case] expected: Vec<u8>,
+    ) {
+        let mut instruction = mnemonic
+            .op1(register::RBP)
+            .op2(src)
+            .disp(Operand::Offset32(-8));
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    #[case::Movsx8(MOVSX8.clone(), vec ! [0x48, 0x0F, 0xBE, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Movsx16(MOVSX16.clone(), vec ! [0x48, 0x0F, 0xBF, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Movsxd(MOVSXD.clone(), vec ! [0x48, 0x63, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Movzx8(MOVZX8.clone(), vec ! [0x48, 0x0F, 0xB6, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    #[case::Movzx16(MOVZX16.clone(), vec ! [0x48, 0x0F, 0xB7, 0x85, 0xF8, 0xFF, 0xFF, 0xFF])]
+    fn test_extend_load(#[case] mnemonic: Mnemonic, #[case] expected: Vec<u8>) {
+        let mut instruction = mnemonic
+            .op1(register::RAX)
+            .op2(register::RBP)
+            .disp(Operand::Offset32(-8));
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    #[case::Movsx8(MOVSX8.clone(), register::AL, vec ! [0x48, 0x0F, 0xBE, 0xC0])]
+    #[case::Movzx16(MOVZX16.clone(), register::AX, vec ! [0x48, 0x0F, 0xB7, 0xC0])]
+    #[case::Movsxd(MOVSXD.clone(), register::EAX, vec ! [0x48, 0x63, 0xC0])]
+    #[case::Imul(IMUL.clone(), register::RCX, vec ! [0x48, 0x0F, 0xAF, 0xC1])]
+    fn test_rm_register(
+        #[case] mnemonic: Mnemonic,
+        #[case] src: register::Register,
+        #[case] expected: Vec<u8>,
+    ) {
+        let mut instruction = mnemonic.op1(register::RAX).op2(src);
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    #[case::RspBase(register::RAX, register::RSP, vec ! [0x48, 0x8B, 0x84, 0x24, 0x08, 0x0, 0x0, 0x0])]
+    #[case::R8Rax(register::R8, register::RAX, vec ! [0x4C, 0x8B, 0x80, 0x08, 0x0, 0x0, 0x0])]
+    fn test_mov_load(
+        #[case] dst: register::Register,
+        #[case] base: register::Register,
+        #[case] expected: Vec<u8>,
+    ) {
+        let mut instruction = MOV.op1(dst).op2(base).disp(Operand::Offset32(8));
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    #[case::Idiv(IDIV.clone(), vec ! [0x48, 0xF7, 0xF9])]
+    #[case::Neg(NEG.clone(), vec ! [0x48, 0xF7, 0xD9])]
+    fn test_unary(#[case] mnemonic: Mnemonic, #[case] expected: Vec<u8>) {
+        let mut instruction = mnemonic.op1(register::RCX);
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    fn test_cqo() {
+        let mut instruction = CQO.no_op();
+        assert_eq!(instruction.as_vec(), vec![0x48, 0x99]);
+    }
+
+    #[rstest]
+    #[case::Setl(SETL.clone(), vec ! [0x0F, 0x9C, 0xC0])]
+    #[case::Setb(SETB.clone(), vec ! [0x0F, 0x92, 0xC0])]
+    #[case::Setne(SETNE.clone(), vec ! [0x0F, 0x95, 0xC0])]
+    fn test_set(#[case] mnemonic: Mnemonic, #[case] expected: Vec<u8>) {
+        let mut instruction = mnemonic.op1(register::AL);
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    fn test_cmp_registers() {
+        let mut instruction = CMP.op1(register::RAX).op2(register::RCX);
+        assert_eq!(instruction.as_vec(), vec![0x48, 0x39, 0xC8]);
+    }
+
+    #[rstest]
+    fn test_cmp_memory() {
+        let mut instruction = CMP
+            .op1(register::RAX)
+            .op2(register::RCX)
+            .disp(Operand::Offset32(0));
+        assert_eq!(
+            instruction.as_vec(),
+            vec![0x48, 0x3B, 0x81, 0x0, 0x0, 0x0, 0x0]
+        );
+    }
+
+    #[rstest]
+    #[case::Shl(SHL.clone(), vec ! [0x48, 0xC1, 0xE0, 0x06])]
+    #[case::Shr(SHR.clone(), vec ! [0x48, 0xC1, 0xE8, 0x06])]
+    fn test_shift(#[case] mnemonic: Mnemonic, #[case] expected: Vec<u8>) {
+        let mut instruction = mnemonic.op1(register::RAX).op2(6_u8);
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
+    #[rstest]
+    #[case::JmpRel(JMP_REL.clone(), vec ! [0xE9, 0x10, 0x0, 0x0, 0x0])]
+    #[case::Jae(JAE.clone(), vec ! [0x0F, 0x83, 0x10, 0x0, 0x0, 0x0])]
+    #[case::Jne(JNE.clone(), vec ! [0x0F, 0x85, 0x10, 0x0, 0x0, 0x0])]
+    #[case::Jo(JO.clone(), vec ! [0x0F, 0x80, 0x10, 0x0, 0x0, 0x0])]
+    fn test_relative_jump(#[case] mnemonic: Mnemonic, #[case] expected: Vec<u8>) {
+        let mut instruction = mnemonic.op1(Operand::Offset32(0x10));
+        assert_eq!(instruction.as_vec(), expected);
+    }
+
     #[rstest]
     #[should_panic]
     fn test_invalid_encoding() {
@@ -1,6 +1,27 @@
 pub mod linux;
+pub mod utf8;
 pub mod windows;
 
+/// Stream the print routines write to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Layout of an integer printed by `print_formatted_int`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IntFormat {
+    /// Minimum number of characters, padded with spaces
+    pub width: usize,
+    /// Pad with zeros after the sign instead
+    pub zero_pad: bool,
+    /// Pad after the number instead of before it
+    pub left_align: bool,
+    pub hex: bool,
+    pub signed: bool,
+}
+
 #[cfg(target_os = "linux")]
 pub use linux::*;
 #[cfg(target_os = "windows")]
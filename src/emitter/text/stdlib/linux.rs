@@ -1,24 +1,255 @@
 use std::mem;
 
-use crate::emitter::text::{abi::linux::*, mnemonics::*, CodeContext};
+use crate::emitter::{
+    text::{abi::linux::*, mnemonics::*, CodeContext},
+    variables,
+};
 
-pub fn print(code_context: &mut CodeContext) {
+use super::{utf8, IntFormat, Stream};
+
+/// Exit status of a program that panicked
+pub const PANIC_EXIT_CODE: u64 = 101;
+
+fn fd(stream: Stream) -> u64 {
+    match stream {
+        Stream::Stdout => STDOUT_FD,
+        Stream::Stderr => STDERR_FD,
+    }
+}
+
+/// Prints the string RDI points to on `stream`
+pub fn print(code_context: &mut CodeContext, stream: Stream) {
+    code_context.add(MOV.op1(ARG_REGISTERS[1]).op2(fd(stream)));
+    print_fd(code_context);
+}
+
+/// Size of the buffer stdout is written into
+const STDOUT_BUFFER_SIZE: u64 = 4096;
+/// stdout modes, object files leave it unbuffered as nothing would flush it
+const STDOUT_UNBUFFERED: u32 = 0;
+const STDOUT_FULLY_BUFFERED: u64 = 1;
+const STDOUT_LINE_BUFFERED: u64 = 2;
+
+/// Buffers stdout, line by line when it is a terminal
+pub fn buffer_stdout(code_context: &mut CodeContext) {
+    code_context.add_slice(&[
+        // Room for the termios struct
+        SUB.op1(register::RSP).op2(64_u32),
+        MOV.op1(register::RDI).op2(STDOUT_FD),
+        MOV.op1(register::RSI).op2(TCGETS),
+        MOV.op1(register::RDX).op2(register::RSP),
+        MOV.op1(register::RAX).op2(SYS_IOCTL),
+        SYSCALL.op1(5_u8),
+        ADD.op1(register::RSP).op2(64_u32),
+        MOV.op1(register::RCX).op2(STDOUT_FULLY_BUFFERED),
+        CMP.op1(register::RAX).op2(0_u32),
+    ]);
+    code_context.add(JNE.op1(Operand::Offset32(0)));
+    let not_a_terminal = code_context.get_pc() - 1;
+    code_context.add(MOV.op1(register::RCX).op2(STDOUT_LINE_BUFFERED));
+    code_context.patch_jump(not_a_terminal);
+    code_context.add_slice(&[
+        MOV.op1(register::R8)
+            .op2(0_u64)
+            .symbol(variables::STDOUT_MODE.to_string()),
+        store_int(register::R8, register::RCX, 0, mem::size_of::<u64>()),
+    ]);
+}
+
+/// Writes the string RDI points to into the file descriptor in RSI
+fn print_fd(code_context: &mut CodeContext) {
+    code_context.call_routine("#print", |code_context| {
+        code_context.add(CMP.op1(ARG_REGISTERS[1]).op2(STDOUT_FD as u32));
+        code_context.add(JNE.op1(Operand::Offset32(0)));
+        let not_stdout = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDOUT_MODE.to_string()),
+            CMP.op1(register::R8)
+                .op2(STDOUT_UNBUFFERED)
+                .disp(Operand::Offset32(0)),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let unbuffered = code_context.get_pc() - 1;
+        buffer_print(code_context);
+        code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let end = code_context.get_pc() - 1;
+
+        code_context.patch_jump(not_stdout);
+        code_context.patch_jump(unbuffered);
+        code_context.add_slice(&[
+            // Copy length value to RDX
+            MOV.op1(ARG_REGISTERS[2])
+                .op2(ARG_REGISTERS[0])
+                .disp(Operand::Offset32(0)),
+            // Move RDI to string pointer
+            ADD.op1(ARG_REGISTERS[0]).op2(mem::size_of::<u64>() as u32),
+            // Swap the string pointer into RSI and the file descriptor into RDI
+            MOV.op1(register::RAX).op2(ARG_REGISTERS[1]),
+            MOV.op1(ARG_REGISTERS[1]).op2(ARG_REGISTERS[0]),
+            MOV.op1(ARG_REGISTERS[0]).op2(register::RAX),
+            MOV.op1(register::RAX).op2(SYS_WRITE),
+            SYSCALL.op1(5_u8),
+        ]);
+        code_context.patch_jump(end);
+    });
+}
+
+/// Appends the string RDI points to to the stdout buffer. The buffer is
+/// flushed when the string does not fit and, for terminals, once it holds a
+/// newline.
+fn buffer_print(code_context: &mut CodeContext) {
+    // RBX points to the string
+    code_context.add_slice(&[
+        PUSH.op1(register::RBX),
+        MOV.op1(register::RBX).op2(ARG_REGISTERS[0]),
+        MOV.op1(register::R8)
+            .op2(0_u64)
+            .symbol(variables::STDOUT_BUFFER.to_string()),
+        MOV.op1(register::RAX)
+            .op2(register::R8)
+            .disp(Operand::Offset32(0)),
+        CMP.op1(register::RAX).op2(0_u32),
+    ]);
+    code_context.add(JNE.op1(Operand::Offset32(0)));
+    let allocated = code_context.get_pc() - 1;
+    code_context.add(
+        MOV.op1(register::RDI)
+            .op2(STDOUT_BUFFER_SIZE + mem::size_of::<u64>() as u64),
+    );
+    alloc(code_context);
     code_context.add_slice(&[
-        // Copy length value to RDX
-        MOV.op1(ARG_REGISTERS[2])
-            .op2(ARG_REGISTERS[0])
+        MOV.op1(register::R8)
+            .op2(0_u64)
+            .symbol(variables::STDOUT_BUFFER.to_string()),
+        store_int(register::R8, register::RAX, 0, mem::size_of::<u64>()),
+    ]);
+    code_context.patch_jump(allocated);
+
+    code_context.add_slice(&[
+        MOV.op1(register::RCX)
+            .op2(register::RAX)
             .disp(Operand::Offset32(0)),
-        // Move RDI to string pointer
-        ADD.op1(ARG_REGISTERS[0]).op2(mem::size_of::<u64>() as u32),
-        // Copy string pointer to RSI
-        MOV.op1(ARG_REGISTERS[1]).op2(ARG_REGISTERS[0]),
+        MOV.op1(register::RDX)
+            .op2(register::RBX)
+            .disp(Operand::Offset32(0)),
+        ADD.op1(register::RCX).op2(register::RDX),
+        MOV.op1(register::RDX).op2(STDOUT_BUFFER_SIZE),
+        CMP.op1(register::RDX).op2(register::RCX),
     ]);
+    code_context.add(JAE.op1(Operand::Offset32(0)));
+    let fits = code_context.get_pc() - 1;
+    flush(code_context);
 
+    // Strings larger than the buffer are written directly
+    code_context.add_slice(&[
+        MOV.op1(register::RCX)
+            .op2(register::RBX)
+            .disp(Operand::Offset32(0)),
+        MOV.op1(register::RDX).op2(STDOUT_BUFFER_SIZE),
+        CMP.op1(register::RCX).op2(register::RDX),
+    ]);
+    code_context.add(JB.op1(Operand::Offset32(0)));
+    let fits_after_flush = code_context.get_pc() - 1;
     code_context.add_slice(&[
         MOV.op1(register::RDI).op2(STDOUT_FD),
-        MOV.op1(register::RAX).op2(SYS_WRITE),
-        SYSCALL.op1(05 as u8),
+        MOV.op1(register::RSI).op2(register::RBX),
+    ]);
+    write(code_context);
+    code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+    let written = code_context.get_pc() - 1;
+
+    code_context.patch_jump(fits);
+    code_context.patch_jump(fits_after_flush);
+    code_context.add_slice(&[
+        MOV.op1(register::R8)
+            .op2(0_u64)
+            .symbol(variables::STDOUT_BUFFER.to_string()),
+        MOV.op1(register::RDI)
+            .op2(register::R8)
+            .disp(Operand::Offset32(0)),
+        MOV.op1(register::RCX)
+            .op2(register::RDI)
+            .disp(Operand::Offset32(0)),
+        MOV.op1(register::RDX)
+            .op2(register::RBX)
+            .disp(Operand::Offset32(0)),
+        MOV.op1(register::RAX).op2(register::RCX),
+        ADD.op1(register::RAX).op2(register::RDX),
+        store_int(register::RDI, register::RAX, 0, mem::size_of::<u64>()),
+        ADD.op1(register::RDI).op2(mem::size_of::<u64>() as u32),
+        ADD.op1(register::RDI).op2(register::RCX),
+        MOV.op1(register::RSI).op2(register::RBX),
+        ADD.op1(register::RSI).op2(mem::size_of::<u64>() as u32),
+    ]);
+    copy_bytes(code_context);
+
+    code_context.add_slice(&[
+        MOV.op1(register::R8)
+            .op2(0_u64)
+            .symbol(variables::STDOUT_MODE.to_string()),
+        MOV.op1(register::RAX)
+            .op2(register::R8)
+            .disp(Operand::Offset32(0)),
+        CMP.op1(register::RAX).op2(STDOUT_LINE_BUFFERED as u32),
+    ]);
+    code_context.add(JNE.op1(Operand::Offset32(0)));
+    let fully_buffered = code_context.get_pc() - 1;
+    code_context.add_slice(&[
+        MOV.op1(register::RSI).op2(register::RBX),
+        ADD.op1(register::RSI).op2(mem::size_of::<u64>() as u32),
+        MOV.op1(register::RDX)
+            .op2(register::RBX)
+            .disp(Operand::Offset32(0)),
     ]);
+    let next_byte = code_context.get_code_size();
+    code_context.add(CMP.op1(register::RDX).op2(0_u32));
+    code_context.add(JE.op1(Operand::Offset32(0)));
+    let no_newline = code_context.get_pc() - 1;
+    code_context.add_slice(&[
+        load_int(register::RCX, register::RSI, 0, 1, false),
+        CMP.op1(register::RCX).op2(0x0A_u32), // ascii code for '\n'
+    ]);
+    code_context.add(JE.op1(Operand::Offset32(0)));
+    let newline = code_context.get_pc() - 1;
+    code_context.add_slice(&[INC.op1(register::RSI), SUB.op1(register::RDX).op2(1_u32)]);
+    code_context.jump_back(&JMP_REL, next_byte);
+    code_context.patch_jump(newline);
+    flush(code_context);
+
+    code_context.patch_jump(written);
+    code_context.patch_jump(fully_buffered);
+    code_context.patch_jump(no_newline);
+    code_context.add(POP.op1(register::RBX));
+}
+
+/// Writes out the bytes buffered for stdout
+pub fn flush(code_context: &mut CodeContext) {
+    code_context.call_routine("#flush", |code_context| {
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDOUT_BUFFER.to_string()),
+            MOV.op1(register::RSI)
+                .op2(register::R8)
+                .disp(Operand::Offset32(0)),
+            CMP.op1(register::RSI).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let no_buffer = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            PUSH.op1(register::RSI),
+            MOV.op1(register::RDI).op2(STDOUT_FD),
+        ]);
+        write(code_context);
+        code_context.add_slice(&[
+            POP.op1(register::RSI),
+            XOR.op1(register::RAX).op2(register::RAX),
+            store_int(register::RSI, register::RAX, 0, mem::size_of::<u64>()),
+        ]);
+        code_context.patch_jump(no_buffer);
+    });
 }
 
 pub fn itoa(code_context: &mut CodeContext) {
@@ -80,26 +311,1195 @@ pub fn itoa(code_context: &mut CodeContext) {
     ]);
 }
 
-pub fn printd(code_context: &mut CodeContext) {
-    itoa(code_context);
+/// Prints the unsigned integer in RDI on `stream`
+pub fn printd(code_context: &mut CodeContext, stream: Stream) {
+    code_context.add(MOV.op1(ARG_REGISTERS[1]).op2(fd(stream)));
+    printd_fd(code_context);
+}
+
+/// Writes the unsigned integer in RDI into the file descriptor in RSI
+fn printd_fd(code_context: &mut CodeContext) {
+    code_context.call_routine("#printd", |code_context| {
+        itoa(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RSP).op2(register::RAX),
+            PUSH.op1(register::R9),
+            SUB.op1(register::RAX).op2(mem::size_of::<u64>() as u32), // make RAX point to length
+            MOV.op1(ARG_REGISTERS[0]).op2(register::RAX),
+        ]);
+        print_fd(code_context);
+
+        code_context.add_slice(&[
+            POP.op1(register::R9),
+            ADD.op1(register::RSP).op2(register::R9), // pop string
+        ]);
+    });
+}
+
+/// Prints the signed integer in RDI on `stream`
+pub fn printi(code_context: &mut CodeContext, stream: Stream) {
+    code_context.add(MOV.op1(ARG_REGISTERS[1]).op2(fd(stream)));
+    code_context.call_routine("#printi", |code_context| {
+        code_context.add(CMP.op1(ARG_REGISTERS[0]).op2(0_u32));
+        code_context.add(JGE.op1(Operand::Offset32(0)));
+        let positive = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            PUSH.op1(ARG_REGISTERS[1]),
+            PUSH.op1(ARG_REGISTERS[0]),
+            PUSH.op1(0x2D_u32), // ascii code for '-'
+            PUSH.op1(1_u32),    // string length
+            MOV.op1(ARG_REGISTERS[0]).op2(register::RSP),
+        ]);
+        print_fd(code_context);
+        code_context.add_slice(&[
+            ADD.op1(register::RSP).op2(16_u32),
+            POP.op1(ARG_REGISTERS[0]),
+            POP.op1(ARG_REGISTERS[1]),
+            NEG.op1(ARG_REGISTERS[0]),
+        ]);
+        code_context.patch_jump(positive);
+
+        printd_fd(code_context);
+    });
+}
+
+/// Prints the char whose code point is in RDI on `stream`
+pub fn print_char(code_context: &mut CodeContext, stream: Stream) {
+    code_context.add(MOV.op1(ARG_REGISTERS[1]).op2(fd(stream)));
+    code_context.call_routine("#print_char", |code_context| {
+        utf8::encode(code_context, ARG_REGISTERS[0]);
+        code_context.add_slice(&[
+            PUSH.op1(register::R8),
+            PUSH.op1(register::R9), // string length
+            MOV.op1(ARG_REGISTERS[0]).op2(register::RSP),
+        ]);
+        print_fd(code_context);
+        code_context.add(ADD.op1(register::RSP).op2(16_u32));
+    });
+}
+
+const FORMAT_ZERO_PAD: u32 = 1;
+const FORMAT_LEFT_ALIGN: u32 = 2;
+const FORMAT_HEX: u32 = 4;
+const FORMAT_SIGNED: u32 = 8;
+
+/// Prints the integer in RDI on `stream` laid out as `format`
+pub fn print_formatted_int(code_context: &mut CodeContext, stream: Stream, format: IntFormat) {
+    let flags = [
+        (format.zero_pad, FORMAT_ZERO_PAD),
+        (format.left_align, FORMAT_LEFT_ALIGN),
+        (format.hex, FORMAT_HEX),
+        (format.signed, FORMAT_SIGNED),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, flag)| flags | flag);
     code_context.add_slice(&[
-        MOV.op1(register::RSP).op2(register::RAX),
-        PUSH.op1(register::R9),
-        SUB.op1(register::RAX).op2(mem::size_of::<u64>() as u32), // make RAX point to length
-        MOV.op1(ARG_REGISTERS[0]).op2(register::RAX),
+        MOV.op1(ARG_REGISTERS[1]).op2(fd(stream)),
+        MOV.op1(ARG_REGISTERS[2]).op2(format.width as u64),
+        MOV.op1(ARG_REGISTERS[3]).op2(flags as u64),
     ]);
-    print(code_context);
+    code_context.call_routine("#print_formatted_int", |code_context| {
+        // The string is built in a stack buffer with room for the padding on
+        // both sides of the digits. R8 points to its first byte, R9 past its
+        // last one and R11 holds the width.
+        code_context.add_slice(&[
+            PUSH.op1(register::RBX),
+            MOV.op1(register::RBX).op2(register::RSP),
+            MOV.op1(register::RAX).op2(register::RDX),
+            SHL.op1(register::RAX).op2(1_u8),
+            ADD.op1(register::RAX).op2(47_u32),
+            AND.op1(register::RAX).op2(-8_i32 as u32),
+            SUB.op1(register::RSP).op2(register::RAX),
+            MOV.op1(register::R8).op2(register::RSP),
+            ADD.op1(register::R8).op2(register::RDX),
+            ADD.op1(register::R8).op2(32_u32),
+            MOV.op1(register::R9).op2(register::R8),
+            MOV.op1(register::R11).op2(register::RDX),
+            MOV.op1(register::R10).op2(10_u64),
+            MOV.op1(register::RAX).op2(register::RCX),
+            AND.op1(register::RAX).op2(FORMAT_HEX),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let decimal = code_context.get_pc() - 1;
+        code_context.add(MOV.op1(register::R10).op2(16_u64));
+        code_context.patch_jump(decimal);
+
+        // RDI is set for negative numbers
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::RDI),
+            XOR.op1(register::RDI).op2(register::RDI),
+            MOV.op1(register::RDX).op2(register::RCX),
+            AND.op1(register::RDX).op2(FORMAT_SIGNED),
+            CMP.op1(register::RDX).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let unsigned = code_context.get_pc() - 1;
+        code_context.add(CMP.op1(register::RAX).op2(0_u32));
+        code_context.add(JGE.op1(Operand::Offset32(0)));
+        let positive = code_context.get_pc() - 1;
+        code_context.add_slice(&[NEG.op1(register::RAX), INC.op1(register::RDI)]);
+        code_context.patch_jump(unsigned);
+        code_context.patch_jump(positive);
+
+        let next_digit = code_context.get_code_size();
+        code_context.add_slice(&[
+            XOR.op1(register::RDX).op2(register::RDX),
+            DIV.op1(register::R10),
+            CMP.op1(register::RDX).op2(10_u32),
+        ]);
+        code_context.add(JB.op1(Operand::Offset32(0)));
+        let decimal_digit = code_context.get_pc() - 1;
+        code_context.add(ADD.op1(register::RDX).op2(0x27_u32)); // from ':' to 'a'
+        code_context.patch_jump(decimal_digit);
+        code_context.add_slice(&[
+            ADD.op1(register::RDX).op2(0x30_u32), // ascii code for '0'
+            SUB.op1(register::R8).op2(1_u32),
+            store_int(register::R8, register::RDX, 0, 1),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        code_context.jump_back(&JNE, next_digit);
+
+        // Zeros go between the sign and the digits
+        code_context.add_slice(&[
+            MOV.op1(register::RDX).op2(register::RCX),
+            AND.op1(register::RDX).op2(FORMAT_ZERO_PAD),
+            CMP.op1(register::RDX).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let no_zeros = code_context.get_pc() - 1;
+        let next_zero = code_context.get_code_size();
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::R9),
+            SUB.op1(register::RAX).op2(register::R8),
+            ADD.op1(register::RAX).op2(register::RDI),
+            CMP.op1(register::RAX).op2(register::R11),
+        ]);
+        code_context.add(JGE.op1(Operand::Offset32(0)));
+        let zero_padded = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            SUB.op1(register::R8).op2(1_u32),
+            MOV.op1(register::RDX).op2(0x30_u64), // ascii code for '0'
+            store_int(register::R8, register::RDX, 0, 1),
+        ]);
+        code_context.jump_back(&JMP_REL, next_zero);
+        code_context.patch_jump(no_zeros);
+        code_context.patch_jump(zero_padded);
 
+        code_context.add(CMP.op1(register::RDI).op2(0_u32));
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let no_sign = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            SUB.op1(register::R8).op2(1_u32),
+            MOV.op1(register::RDX).op2(0x2D_u64), // ascii code for '-'
+            store_int(register::R8, register::RDX, 0, 1),
+        ]);
+        code_context.patch_jump(no_sign);
+
+        // Spaces fill the rest of the width on either side
+        code_context.add_slice(&[
+            MOV.op1(register::RDX).op2(0x20_u64), // ascii code for ' '
+            MOV.op1(register::RAX).op2(register::RCX),
+            AND.op1(register::RAX).op2(FORMAT_LEFT_ALIGN),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let right_aligned = code_context.get_pc() - 1;
+        let next_space_after = code_context.get_code_size();
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::R9),
+            SUB.op1(register::RAX).op2(register::R8),
+            CMP.op1(register::RAX).op2(register::R11),
+        ]);
+        code_context.add(JGE.op1(Operand::Offset32(0)));
+        let padded_after = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            store_int(register::R9, register::RDX, 0, 1),
+            INC.op1(register::R9),
+        ]);
+        code_context.jump_back(&JMP_REL, next_space_after);
+
+        code_context.patch_jump(right_aligned);
+        let next_space_before = code_context.get_code_size();
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::R9),
+            SUB.op1(register::RAX).op2(register::R8),
+            CMP.op1(register::RAX).op2(register::R11),
+        ]);
+        code_context.add(JGE.op1(Operand::Offset32(0)));
+        let padded_before = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            SUB.op1(register::R8).op2(1_u32),
+            store_int(register::R8, register::RDX, 0, 1),
+        ]);
+        code_context.jump_back(&JMP_REL, next_space_before);
+
+        code_context.patch_jump(padded_after);
+        code_context.patch_jump(padded_before);
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::R9),
+            SUB.op1(register::RAX).op2(register::R8),
+            SUB.op1(register::R8).op2(mem::size_of::<u64>() as u32),
+            store_int(register::R8, register::RAX, 0, mem::size_of::<u64>()),
+            MOV.op1(ARG_REGISTERS[0]).op2(register::R8),
+        ]);
+        print_fd(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RSP).op2(register::RBX),
+            POP.op1(register::RBX),
+        ]);
+    });
+}
+
+/// Flushes stdout and exits with the status held in `exit_code`
+pub fn exit(code_context: &mut CodeContext, exit_code: register::Register) {
+    code_context.add(PUSH.op1(exit_code));
+    flush(code_context);
     code_context.add_slice(&[
-        POP.op1(register::R9),
-        ADD.op1(register::RSP).op2(register::R9), // pop string
+        POP.op1(register::RDI),
+        MOV.op1(register::RAX).op2(SYS_EXIT),
+        SYSCALL.op1(5_u8),
     ]);
 }
 
-pub fn exit(code_context: &mut CodeContext, exit_code: u64) {
+/// Heap memory is mapped in chunks of at least this size
+const HEAP_CHUNK_SIZE: u64 = 1 << 20;
+
+/// Bump allocates RDI bytes, rounded up to 8, and puts the address into RAX.
+/// Memory is never freed. Clobbers RCX, RDX, RSI, R8-R11.
+pub fn alloc(code_context: &mut CodeContext) {
+    code_context.call_routine("#alloc", |code_context| {
+        code_context.add_slice(&[
+            ADD.op1(register::RDI).op2(7_u32),
+            AND.op1(register::RDI).op2(-8_i32 as u32),
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::HEAP_TOP.to_string()),
+            MOV.op1(register::RAX)
+                .op2(register::R8)
+                .disp(Operand::Offset32(0)),
+            MOV.op1(register::RDX).op2(register::RAX),
+            ADD.op1(register::RDX).op2(register::RDI),
+            MOV.op1(register::R9)
+                .op2(0_u64)
+                .symbol(variables::HEAP_END.to_string()),
+            MOV.op1(register::R10)
+                .op2(register::R9)
+                .disp(Operand::Offset32(0)),
+            CMP.op1(register::R10).op2(register::RDX),
+        ]);
+        code_context.add(JAE.op1(Operand::Offset32(0)));
+        let fits = code_context.get_pc() - 1;
+
+        // Map a new chunk, large allocations get one of their own
+        code_context.add_slice(&[
+            MOV.op1(register::RSI).op2(HEAP_CHUNK_SIZE),
+            CMP.op1(register::RSI).op2(register::RDI),
+        ]);
+        code_context.add(JAE.op1(Operand::Offset32(0)));
+        let chunk_size = code_context.get_pc() - 1;
+        code_context.add(MOV.op1(register::RSI).op2(register::RDI));
+        code_context.patch_jump(chunk_size);
+
+        code_context.add_slice(&[
+            PUSH.op1(register::RDI),
+            PUSH.op1(register::RSI),
+            XOR.op1(register::RDI).op2(register::RDI),
+            MOV.op1(register::RDX).op2(PROT_READ_WRITE),
+            MOV.op1(register::R10).op2(MAP_PRIVATE_ANONYMOUS),
+            MOV.op1(register::R8).op2(u64::MAX), // no file descriptor
+            XOR.op1(register::R9).op2(register::R9),
+            MOV.op1(register::RAX).op2(SYS_MMAP),
+            SYSCALL.op1(5_u8),
+            POP.op1(register::RSI),
+            POP.op1(register::RDI),
+            MOV.op1(register::R9)
+                .op2(0_u64)
+                .symbol(variables::HEAP_END.to_string()),
+            MOV.op1(register::RDX).op2(register::RAX),
+            ADD.op1(register::RDX).op2(register::RSI),
+            store_int(register::R9, register::RDX, 0, mem::size_of::<u64>()),
+            MOV.op1(register::RDX).op2(register::RAX),
+            ADD.op1(register::RDX).op2(register::RDI),
+        ]);
+        code_context.patch_jump(fits);
+
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::HEAP_TOP.to_string()),
+            store_int(register::R8, register::RDX, 0, mem::size_of::<u64>()),
+        ]);
+    });
+}
+
+/// Copies the NUL terminated string RSI points to into a new string and puts
+/// its address into RAX. A null pointer gives an empty string. Clobbers RCX,
+/// RDX, RSI, RDI, R8-R11.
+pub fn from_c_string(code_context: &mut CodeContext) {
+    code_context.call_routine("#from_c_string", |code_context| {
+        code_context.add_slice(&[
+            XOR.op1(register::RDX).op2(register::RDX),
+            CMP.op1(register::RSI).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let null = code_context.get_pc() - 1;
+
+        let strlen = code_context.get_code_size();
+        code_context.add_slice(&[
+            MOV.op1(register::RCX).op2(register::RSI),
+            ADD.op1(register::RCX).op2(register::RDX),
+            load_int(register::RCX, register::RCX, 0, 1, false),
+            CMP.op1(register::RCX).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let end_of_string = code_context.get_pc() - 1;
+        code_context.add(INC.op1(register::RDX));
+        code_context.jump_back(&JMP_REL, strlen);
+        code_context.patch_jump(null);
+        code_context.patch_jump(end_of_string);
+
+        // Length, bytes and NUL, fresh memory is already zeroed
+        code_context.add_slice(&[
+            PUSH.op1(register::RSI),
+            PUSH.op1(register::RDX),
+            MOV.op1(register::RDI).op2(register::RDX),
+            ADD.op1(register::RDI)
+                .op2((mem::size_of::<u64>() + 1) as u32),
+        ]);
+        alloc(code_context);
+        code_context.add_slice(&[
+            POP.op1(register::RDX),
+            POP.op1(register::RSI),
+            store_int(register::RAX, register::RDX, 0, mem::size_of::<u64>()),
+            MOV.op1(register::RDI).op2(register::RAX),
+            ADD.op1(register::RDI).op2(mem::size_of::<u64>() as u32),
+        ]);
+        copy_bytes(code_context);
+    });
+}
+
+/// Copies the string RAX points to into a new string and puts its address
+/// into RAX. Clobbers RCX, RDX, RSI, RDI, R8-R11.
+pub fn copy_string(code_context: &mut CodeContext) {
+    code_context.call_routine("#copy_string", |code_context| {
+        code_context.add_slice(&[
+            PUSH.op1(register::RAX),
+            MOV.op1(register::RDI)
+                .op2(register::RAX)
+                .disp(Operand::Offset32(0)),
+            ADD.op1(register::RDI)
+                .op2((mem::size_of::<u64>() + 1) as u32),
+        ]);
+        alloc(code_context);
+        // Length and bytes, fresh memory is already zeroed
+        code_context.add_slice(&[
+            POP.op1(register::RSI),
+            PUSH.op1(register::RAX),
+            MOV.op1(register::RDI).op2(register::RAX),
+            MOV.op1(register::RDX)
+                .op2(register::RSI)
+                .disp(Operand::Offset32(0)),
+            ADD.op1(register::RDX).op2(mem::size_of::<u64>() as u32),
+        ]);
+        copy_bytes(code_context);
+        code_context.add(POP.op1(register::RAX));
+    });
+}
+
+/// Puts the number of command-line arguments into RAX
+pub fn arg_count(code_context: &mut CodeContext) {
     code_context.add_slice(&[
-        MOV.op1(register::RDI).op2(exit_code),
-        MOV.op1(register::RAX).op2(SYS_EXIT),
-        SYSCALL.op1(05 as u8),
+        MOV.op1(register::RAX)
+            .op2(0_u64)
+            .symbol(variables::STACK_BASE.to_string()),
+        MOV.op1(register::RAX)
+            .op2(register::RAX)
+            .disp(Operand::Offset32(0)),
+        MOV.op1(register::RAX)
+            .op2(register::RAX)
+            .disp(Operand::Offset32(0)),
+    ]);
+}
+
+/// Puts the error code of the last read_file into RAX, 0 if it succeeded
+pub fn file_error(code_context: &mut CodeContext) {
+    code_context.add_slice(&[
+        MOV.op1(register::RAX)
+            .op2(0_u64)
+            .symbol(variables::FILE_ERROR.to_string()),
+        MOV.op1(register::RAX)
+            .op2(register::RAX)
+            .disp(Operand::Offset32(0)),
+    ]);
+}
+
+/// Copies the command-line argument at the index in RAX into a new string
+/// and puts its address into RAX, out of range arguments are empty
+pub fn arg(code_context: &mut CodeContext) {
+    code_context.call_routine("#arg", |code_context| {
+        // The initial stack holds argc, then the argv pointers
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(0_u64)
+                .symbol(variables::STACK_BASE.to_string()),
+            MOV.op1(register::RCX)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(0)),
+            XOR.op1(register::RSI).op2(register::RSI),
+            CMP.op1(register::RAX)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(0)),
+        ]);
+        code_context.add(JAE.op1(Operand::Offset32(0)));
+        let out_of_range = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            SHL.op1(register::RAX).op2(3_u8),
+            ADD.op1(register::RCX).op2(register::RAX),
+            MOV.op1(register::RSI)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(mem::size_of::<u64>() as i32)),
+        ]);
+        code_context.patch_jump(out_of_range);
+        from_c_string(code_context);
+    });
+}
+
+/// Looks up the environment variable named by the string RAX points to and
+/// puts the address of a copy of its value into RAX, unset variables are
+/// empty
+pub fn env(code_context: &mut CodeContext) {
+    code_context.call_routine("#env", |code_context| {
+        // envp follows the argv pointers and their terminating null
+        code_context.add_slice(&[
+            MOV.op1(register::R10).op2(register::RAX),
+            MOV.op1(register::RCX)
+                .op2(0_u64)
+                .symbol(variables::STACK_BASE.to_string()),
+            MOV.op1(register::RCX)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(0)),
+            MOV.op1(register::RAX)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(0)),
+            ADD.op1(register::RAX).op2(2_u32),
+            SHL.op1(register::RAX).op2(3_u8),
+            ADD.op1(register::RCX).op2(register::RAX),
+        ]);
+
+        let next_entry = code_context.get_code_size();
+        code_context.add_slice(&[
+            MOV.op1(register::RSI)
+                .op2(register::RCX)
+                .disp(Operand::Offset32(0)),
+            CMP.op1(register::RSI).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let not_found = code_context.get_pc() - 1;
+
+        // Compare the entry with the name byte by byte
+        code_context.add(XOR.op1(register::RDX).op2(register::RDX));
+        let compare = code_context.get_code_size();
+        code_context.add(
+            CMP.op1(register::RDX)
+                .op2(register::R10)
+                .disp(Operand::Offset32(0)),
+        );
+        code_context.add(JAE.op1(Operand::Offset32(0)));
+        let name_matches = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            MOV.op1(register::RDI).op2(register::RSI),
+            ADD.op1(register::RDI).op2(register::RDX),
+            load_int(register::RAX, register::RDI, 0, 1, false),
+            MOV.op1(register::RDI).op2(register::R10),
+            ADD.op1(register::RDI).op2(register::RDX),
+            load_int(
+                register::RDI,
+                register::RDI,
+                mem::size_of::<u64>() as i32,
+                1,
+                false,
+            ),
+            CMP.op1(register::RAX).op2(register::RDI),
+        ]);
+        code_context.add(JNE.op1(Operand::Offset32(0)));
+        let mismatch = code_context.get_pc() - 1;
+        code_context.add(INC.op1(register::RDX));
+        code_context.jump_back(&JMP_REL, compare);
+
+        // The name must be followed by '='
+        code_context.patch_jump(name_matches);
+        code_context.add_slice(&[
+            MOV.op1(register::RDI).op2(register::RSI),
+            ADD.op1(register::RDI).op2(register::RDX),
+            load_int(register::RAX, register::RDI, 0, 1, false),
+            CMP.op1(register::RAX).op2(0x3D_u32), // ascii code for '='
+        ]);
+        code_context.add(JNE.op1(Operand::Offset32(0)));
+        let no_equal_sign = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            INC.op1(register::RDI),
+            MOV.op1(register::RSI).op2(register::RDI),
+        ]);
+        code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let found = code_context.get_pc() - 1;
+
+        code_context.patch_jump(mismatch);
+        code_context.patch_jump(no_equal_sign);
+        code_context.add(ADD.op1(register::RCX).op2(mem::size_of::<u64>() as u32));
+        code_context.jump_back(&JMP_REL, next_entry);
+
+        code_context.patch_jump(not_found);
+        code_context.patch_jump(found);
+        from_c_string(code_context);
+    });
+}
+
+/// Size of the buffer stdin is read into
+const STDIN_BUFFER_SIZE: u64 = 4096;
+/// Capacity of the string read_line starts with, it doubles when full
+const LINE_CAPACITY: u32 = 64;
+
+/// Puts the next byte of stdin into RAX without consuming it, -1 at the end of
+/// input. Refills the stdin buffer when it is empty. Clobbers RCX, RDX, RSI,
+/// RDI, R8-R11.
+pub fn peek_stdin(code_context: &mut CodeContext) {
+    code_context.call_routine("#peek_stdin", |code_context| {
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDIN_START.to_string()),
+            MOV.op1(register::RAX)
+                .op2(register::R8)
+                .disp(Operand::Offset32(0)),
+            MOV.op1(register::R9)
+                .op2(0_u64)
+                .symbol(variables::STDIN_END.to_string()),
+            CMP.op1(register::RAX)
+                .op2(register::R9)
+                .disp(Operand::Offset32(0)),
+        ]);
+        code_context.add(JB.op1(Operand::Offset32(0)));
+        let buffered = code_context.get_pc() - 1;
+
+        // The buffer is allocated on the first read
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDIN_BUFFER.to_string()),
+            MOV.op1(register::RSI)
+                .op2(register::R8)
+                .disp(Operand::Offset32(0)),
+            CMP.op1(register::RSI).op2(0_u32),
+        ]);
+        code_context.add(JNE.op1(Operand::Offset32(0)));
+        let allocated = code_context.get_pc() - 1;
+        code_context.add(MOV.op1(register::RDI).op2(STDIN_BUFFER_SIZE));
+        alloc(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RSI).op2(register::RAX),
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDIN_BUFFER.to_string()),
+            store_int(register::R8, register::RSI, 0, mem::size_of::<u64>()),
+        ]);
+        code_context.patch_jump(allocated);
+
+        code_context.add_slice(&[
+            PUSH.op1(register::RSI),
+            MOV.op1(register::RDI).op2(STDIN_FD),
+            MOV.op1(register::RDX).op2(STDIN_BUFFER_SIZE),
+            MOV.op1(register::RAX).op2(SYS_READ),
+            SYSCALL.op1(5_u8),
+            POP.op1(register::RSI),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        // Errors end the input too
+        code_context.add(JG.op1(Operand::Offset32(0)));
+        let filled = code_context.get_pc() - 1;
+        code_context.add(MOV.op1(register::RAX).op2(u64::MAX));
+        code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let end_of_input = code_context.get_pc() - 1;
+
+        code_context.patch_jump(filled);
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDIN_START.to_string()),
+            store_int(register::R8, register::RSI, 0, mem::size_of::<u64>()),
+            ADD.op1(register::RAX).op2(register::RSI),
+            MOV.op1(register::R9)
+                .op2(0_u64)
+                .symbol(variables::STDIN_END.to_string()),
+            store_int(register::R9, register::RAX, 0, mem::size_of::<u64>()),
+            MOV.op1(register::RAX).op2(register::RSI),
+        ]);
+
+        code_context.patch_jump(buffered);
+        code_context.add(load_int(register::RAX, register::RAX, 0, 1, false));
+        code_context.patch_jump(end_of_input);
+    });
+}
+
+/// Consumes the byte of stdin peek_stdin returned. Clobbers R8.
+pub fn consume_stdin(code_context: &mut CodeContext) {
+    code_context.add_slice(&[
+        MOV.op1(register::R8)
+            .op2(0_u64)
+            .symbol(variables::STDIN_START.to_string()),
+        INC.op1(register::R8).disp(Operand::Offset32(0)),
     ]);
 }
+
+/// Reads a line from stdin into a new string and puts its address into RAX.
+/// The line keeps its '\n', so an empty string means the input has ended.
+pub fn read_line(code_context: &mut CodeContext) {
+    code_context.call_routine("#read_line", |code_context| {
+        // RBX points to the string and R12 holds its capacity
+        code_context.add_slice(&[
+            PUSH.op1(register::RBX),
+            PUSH.op1(register::R12),
+            MOV.op1(register::RDI)
+                .op2((LINE_CAPACITY as usize + mem::size_of::<u64>() + 1) as u64),
+        ]);
+        alloc(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RBX).op2(register::RAX),
+            MOV.op1(register::R12).op2(LINE_CAPACITY as u64),
+        ]);
+
+        let next_byte = code_context.get_code_size();
+        peek_stdin(code_context);
+        code_context.add(CMP.op1(register::RAX).op2(0_u32));
+        code_context.add(JL.op1(Operand::Offset32(0)));
+        let end_of_input = code_context.get_pc() - 1;
+        consume_stdin(code_context);
+
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(register::RBX)
+                .disp(Operand::Offset32(0)),
+            CMP.op1(register::RCX).op2(register::R12),
+        ]);
+        code_context.add(JB.op1(Operand::Offset32(0)));
+        let has_room = code_context.get_pc() - 1;
+
+        code_context.add(PUSH.op1(register::RAX));
+        grow_string(code_context);
+        code_context.add(POP.op1(register::RAX));
+        code_context.patch_jump(has_room);
+
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(register::RBX)
+                .disp(Operand::Offset32(0)),
+            MOV.op1(register::RDX).op2(register::RBX),
+            ADD.op1(register::RDX).op2(register::RCX),
+            store_int(
+                register::RDX,
+                register::RAX,
+                mem::size_of::<u64>() as i32,
+                1,
+            ),
+            INC.op1(register::RBX).disp(Operand::Offset32(0)),
+            CMP.op1(register::RAX).op2(0x0A_u32), // ascii code for '\n'
+        ]);
+        code_context.jump_back(&JNE, next_byte);
+
+        code_context.patch_jump(end_of_input);
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::RBX),
+            POP.op1(register::R12),
+            POP.op1(register::RBX),
+        ]);
+    });
+}
+
+/// Reads a decimal integer from stdin into RAX, skipping leading whitespace.
+/// Gives 0 when no digits follow.
+pub fn read_int(code_context: &mut CodeContext) {
+    code_context.call_routine("#read_int", |code_context| {
+        let skip_whitespace = code_context.get_code_size();
+        peek_stdin(code_context);
+        let mut whitespace = vec![];
+        for c in [b' ', b'\t', b'\n', b'\r'] {
+            code_context.add(CMP.op1(register::RAX).op2(c as u32));
+            code_context.add(JE.op1(Operand::Offset32(0)));
+            whitespace.push(code_context.get_pc() - 1);
+        }
+        code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let number = code_context.get_pc() - 1;
+        for jump in whitespace {
+            code_context.patch_jump(jump);
+        }
+        consume_stdin(code_context);
+        code_context.jump_back(&JMP_REL, skip_whitespace);
+        code_context.patch_jump(number);
+
+        // RBX accumulates the value and R12 is set for negative numbers
+        code_context.add_slice(&[
+            PUSH.op1(register::RBX),
+            PUSH.op1(register::R12),
+            XOR.op1(register::RBX).op2(register::RBX),
+            XOR.op1(register::R12).op2(register::R12),
+            CMP.op1(register::RAX).op2(0x2D_u32), // ascii code for '-'
+        ]);
+        code_context.add(JNE.op1(Operand::Offset32(0)));
+        let positive = code_context.get_pc() - 1;
+        code_context.add(INC.op1(register::R12));
+        consume_stdin(code_context);
+        code_context.patch_jump(positive);
+
+        let next_digit = code_context.get_code_size();
+        peek_stdin(code_context);
+        code_context.add(CMP.op1(register::RAX).op2(0x30_u32)); // ascii code for '0'
+        code_context.add(JL.op1(Operand::Offset32(0)));
+        let below_digits = code_context.get_pc() - 1;
+        code_context.add(CMP.op1(register::RAX).op2(0x39_u32)); // ascii code for '9'
+        code_context.add(JG.op1(Operand::Offset32(0)));
+        let above_digits = code_context.get_pc() - 1;
+        consume_stdin(code_context);
+        code_context.add_slice(&[
+            SUB.op1(register::RAX).op2(0x30_u32),
+            MOV.op1(register::RCX).op2(10_u64),
+            IMUL.op1(register::RBX).op2(register::RCX),
+            ADD.op1(register::RBX).op2(register::RAX),
+        ]);
+        code_context.jump_back(&JMP_REL, next_digit);
+        code_context.patch_jump(below_digits);
+        code_context.patch_jump(above_digits);
+
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::RBX),
+            CMP.op1(register::R12).op2(0_u32),
+        ]);
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let not_negative = code_context.get_pc() - 1;
+        code_context.add(NEG.op1(register::RAX));
+        code_context.patch_jump(not_negative);
+        code_context.add_slice(&[POP.op1(register::R12), POP.op1(register::RBX)]);
+    });
+}
+
+/// Reads at most RDX bytes of stdin into the string RDI points to and puts
+/// the number of bytes read into RAX, 0 at the end of input. Like the read
+/// syscall it returns early rather than wait for more input.
+pub fn read(code_context: &mut CodeContext) {
+    code_context.call_routine("#read", |code_context| {
+        // RBX points to the string, R12 holds the limit and R13 the count
+        code_context.add_slice(&[
+            PUSH.op1(register::RBX),
+            PUSH.op1(register::R12),
+            PUSH.op1(register::R13),
+            MOV.op1(register::RBX).op2(register::RDI),
+            MOV.op1(register::R12).op2(register::RDX),
+            XOR.op1(register::R13).op2(register::R13),
+        ]);
+
+        let next_byte = code_context.get_code_size();
+        code_context.add(CMP.op1(register::R13).op2(register::R12));
+        code_context.add(JAE.op1(Operand::Offset32(0)));
+        let limit_reached = code_context.get_pc() - 1;
+
+        // Only the first byte may wait for input
+        code_context.add(CMP.op1(register::R13).op2(0_u32));
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let first_byte = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            MOV.op1(register::R8)
+                .op2(0_u64)
+                .symbol(variables::STDIN_START.to_string()),
+            MOV.op1(register::RAX)
+                .op2(register::R8)
+                .disp(Operand::Offset32(0)),
+            MOV.op1(register::R9)
+                .op2(0_u64)
+                .symbol(variables::STDIN_END.to_string()),
+            CMP.op1(register::RAX)
+                .op2(register::R9)
+                .disp(Operand::Offset32(0)),
+        ]);
+        code_context.add(JAE.op1(Operand::Offset32(0)));
+        let buffer_drained = code_context.get_pc() - 1;
+        code_context.patch_jump(first_byte);
+
+        peek_stdin(code_context);
+        code_context.add(CMP.op1(register::RAX).op2(0_u32));
+        code_context.add(JL.op1(Operand::Offset32(0)));
+        let end_of_input = code_context.get_pc() - 1;
+        consume_stdin(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RCX).op2(register::RBX),
+            ADD.op1(register::RCX).op2(register::R13),
+            store_int(
+                register::RCX,
+                register::RAX,
+                mem::size_of::<u64>() as i32,
+                1,
+            ),
+            INC.op1(register::R13),
+        ]);
+        code_context.jump_back(&JMP_REL, next_byte);
+
+        code_context.patch_jump(limit_reached);
+        code_context.patch_jump(buffer_drained);
+        code_context.patch_jump(end_of_input);
+        code_context.add_slice(&[
+            store_int(register::RBX, register::R13, 0, mem::size_of::<u64>()),
+            MOV.op1(register::RCX).op2(register::RBX),
+            ADD.op1(register::RCX).op2(register::R13),
+            XOR.op1(register::RAX).op2(register::RAX),
+            store_int(
+                register::RCX,
+                register::RAX,
+                mem::size_of::<u64>() as i32,
+                1,
+            ),
+            MOV.op1(register::RAX).op2(register::R13),
+            POP.op1(register::R13),
+            POP.op1(register::R12),
+            POP.op1(register::RBX),
+        ]);
+    });
+}
+
+/// Moves the string RBX points to into a new string of twice the capacity
+/// held in R12, and updates both. Clobbers RAX, RCX, RDX, RSI, RDI, R8-R11.
+fn grow_string(code_context: &mut CodeContext) {
+    code_context.call_routine("#grow_string", |code_context| {
+        code_context.add_slice(&[
+            SHL.op1(register::R12).op2(1_u8),
+            MOV.op1(register::RDI).op2(register::R12),
+            ADD.op1(register::RDI)
+                .op2((mem::size_of::<u64>() + 1) as u32),
+        ]);
+        alloc(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RSI).op2(register::RBX),
+            MOV.op1(register::RBX).op2(register::RAX),
+            MOV.op1(register::RDI).op2(register::RAX),
+            MOV.op1(register::RDX)
+                .op2(register::RSI)
+                .disp(Operand::Offset32(0)),
+            ADD.op1(register::RDX).op2(mem::size_of::<u64>() as u32),
+        ]);
+        copy_bytes(code_context);
+    });
+}
+
+/// Copies RDX bytes from RSI to RDI. Clobbers RCX.
+fn copy_bytes(code_context: &mut CodeContext) {
+    code_context.call_routine("#copy_bytes", |code_context| {
+        let copy = code_context.get_code_size();
+        code_context.add(CMP.op1(register::RDX).op2(0_u32));
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let copied = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            load_int(register::RCX, register::RSI, 0, 1, false),
+            store_int(register::RDI, register::RCX, 0, 1),
+            INC.op1(register::RSI),
+            INC.op1(register::RDI),
+            SUB.op1(register::RDX).op2(1_u32),
+        ]);
+        code_context.jump_back(&JMP_REL, copy);
+        code_context.patch_jump(copied);
+    });
+}
+
+/// Capacity of the string read_file starts with, it doubles when full
+const FILE_CAPACITY: u32 = 4096;
+
+/// Opens the file whose path is the string RAX points to and puts the file
+/// descriptor into RAX, or a negative error code. `mode` is one of the fopen
+/// modes r, w, a, r+, w+ and a+.
+pub fn open(code_context: &mut CodeContext, mode: &str) {
+    let flags = match mode {
+        "r" => O_RDONLY,
+        "w" => O_WRONLY | O_CREAT | O_TRUNC,
+        "a" => O_WRONLY | O_CREAT | O_APPEND,
+        "r+" => O_RDWR,
+        "w+" => O_RDWR | O_CREAT | O_TRUNC,
+        "a+" => O_RDWR | O_CREAT | O_APPEND,
+        _ => panic!("Invalid file mode: {mode:?}"),
+    };
+    code_context.add_slice(&[
+        MOV.op1(register::RDI).op2(register::RAX),
+        ADD.op1(register::RDI).op2(mem::size_of::<u64>() as u32),
+        MOV.op1(register::RSI).op2(flags),
+        MOV.op1(register::RDX).op2(FILE_PERMISSIONS),
+        MOV.op1(register::RAX).op2(SYS_OPEN),
+        SYSCALL.op1(5_u8),
+    ]);
+}
+
+/// Closes the file descriptor in RDI, RAX is 0 or a negative error code
+pub fn close(code_context: &mut CodeContext) {
+    code_context.add_slice(&[MOV.op1(register::RAX).op2(SYS_CLOSE), SYSCALL.op1(5_u8)]);
+}
+
+/// Writes the whole string RSI points to into the file descriptor in RDI.
+/// Puts the number of bytes written into RAX, or a negative error code.
+pub fn write(code_context: &mut CodeContext) {
+    code_context.call_routine("#write", |code_context| {
+        // RBX holds the file descriptor, R12 the next byte and R13 the number of
+        // bytes left
+        code_context.add_slice(&[
+            PUSH.op1(register::RBX),
+            PUSH.op1(register::R12),
+            PUSH.op1(register::R13),
+            MOV.op1(register::RBX).op2(register::RDI),
+            MOV.op1(register::R12).op2(register::RSI),
+            ADD.op1(register::R12).op2(mem::size_of::<u64>() as u32),
+            MOV.op1(register::R13)
+                .op2(register::RSI)
+                .disp(Operand::Offset32(0)),
+            PUSH.op1(register::R13),
+        ]);
+
+        // The kernel may write less than asked
+        let next_write = code_context.get_code_size();
+        code_context.add(CMP.op1(register::R13).op2(0_u32));
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let all_written = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            MOV.op1(register::RDI).op2(register::RBX),
+            MOV.op1(register::RSI).op2(register::R12),
+            MOV.op1(register::RDX).op2(register::R13),
+            MOV.op1(register::RAX).op2(SYS_WRITE),
+            SYSCALL.op1(5_u8),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        code_context.add(JL.op1(Operand::Offset32(0)));
+        let failed = code_context.get_pc() - 1;
+        code_context.add(JE.op1(Operand::Offset32(0)));
+        let stalled = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            ADD.op1(register::R12).op2(register::RAX),
+            SUB.op1(register::R13).op2(register::RAX),
+        ]);
+        code_context.jump_back(&JMP_REL, next_write);
+
+        code_context.patch_jump(failed);
+        code_context.add(POP.op1(register::RCX));
+        code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let end = code_context.get_pc() - 1;
+
+        code_context.patch_jump(all_written);
+        code_context.patch_jump(stalled);
+        code_context.add_slice(&[
+            POP.op1(register::RAX),
+            SUB.op1(register::RAX).op2(register::R13),
+        ]);
+
+        code_context.patch_jump(end);
+        code_context.add_slice(&[
+            POP.op1(register::R13),
+            POP.op1(register::R12),
+            POP.op1(register::RBX),
+        ]);
+    });
+}
+
+/// Reads at most RDX bytes from the file descriptor in RDI into the string
+/// RSI points to. Puts the number of bytes read into RAX, or a negative error
+/// code.
+pub fn read_fd(code_context: &mut CodeContext) {
+    code_context.call_routine("#read_fd", |code_context| {
+        code_context.add_slice(&[
+            PUSH.op1(register::RSI),
+            ADD.op1(register::RSI).op2(mem::size_of::<u64>() as u32),
+            MOV.op1(register::RAX).op2(SYS_READ),
+            SYSCALL.op1(5_u8),
+            POP.op1(register::RSI),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        code_context.add(JL.op1(Operand::Offset32(0)));
+        let failed = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            store_int(register::RSI, register::RAX, 0, mem::size_of::<u64>()),
+            MOV.op1(register::RCX).op2(register::RSI),
+            ADD.op1(register::RCX).op2(register::RAX),
+            XOR.op1(register::RDX).op2(register::RDX),
+            store_int(
+                register::RCX,
+                register::RDX,
+                mem::size_of::<u64>() as i32,
+                1,
+            ),
+        ]);
+        code_context.patch_jump(failed);
+    });
+}
+
+/// Reads the file whose path is the string RAX points to into a new string
+/// and puts its address into RAX. Files that cannot be opened read as empty
+/// and leave the negative error code for file_error.
+pub fn read_file(code_context: &mut CodeContext) {
+    code_context.call_routine("#read_file", |code_context| {
+        open(code_context, "r");
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(0_u64)
+                .symbol(variables::FILE_ERROR.to_string()),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        code_context.add(JGE.op1(Operand::Offset32(0)));
+        let opened = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            store_int(register::RCX, register::RAX, 0, mem::size_of::<u64>()),
+            XOR.op1(register::RSI).op2(register::RSI),
+        ]);
+        from_c_string(code_context);
+        code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+        let end = code_context.get_pc() - 1;
+        code_context.patch_jump(opened);
+        code_context.add_slice(&[
+            XOR.op1(register::RDX).op2(register::RDX),
+            store_int(register::RCX, register::RDX, 0, mem::size_of::<u64>()),
+        ]);
+
+        // RBX points to the string, R12 holds its capacity and R13 the file
+        // descriptor
+        code_context.add_slice(&[
+            PUSH.op1(register::RBX),
+            PUSH.op1(register::R12),
+            PUSH.op1(register::R13),
+            MOV.op1(register::R13).op2(register::RAX),
+            MOV.op1(register::RDI)
+                .op2((FILE_CAPACITY as usize + mem::size_of::<u64>() + 1) as u64),
+        ]);
+        alloc(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RBX).op2(register::RAX),
+            MOV.op1(register::R12).op2(FILE_CAPACITY as u64),
+        ]);
+
+        let next_read = code_context.get_code_size();
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(register::RBX)
+                .disp(Operand::Offset32(0)),
+            CMP.op1(register::RCX).op2(register::R12),
+        ]);
+        code_context.add(JB.op1(Operand::Offset32(0)));
+        let has_room = code_context.get_pc() - 1;
+        grow_string(code_context);
+        code_context.patch_jump(has_room);
+
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(register::RBX)
+                .disp(Operand::Offset32(0)),
+            MOV.op1(register::RDI).op2(register::R13),
+            MOV.op1(register::RSI).op2(register::RBX),
+            ADD.op1(register::RSI).op2(mem::size_of::<u64>() as u32),
+            ADD.op1(register::RSI).op2(register::RCX),
+            MOV.op1(register::RDX).op2(register::R12),
+            SUB.op1(register::RDX).op2(register::RCX),
+            MOV.op1(register::RAX).op2(SYS_READ),
+            SYSCALL.op1(5_u8),
+            CMP.op1(register::RAX).op2(0_u32),
+        ]);
+        // Read errors end the file like its end does
+        code_context.add(JLE.op1(Operand::Offset32(0)));
+        let end_of_file = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            MOV.op1(register::RCX)
+                .op2(register::RBX)
+                .disp(Operand::Offset32(0)),
+            ADD.op1(register::RCX).op2(register::RAX),
+            store_int(register::RBX, register::RCX, 0, mem::size_of::<u64>()),
+        ]);
+        code_context.jump_back(&JMP_REL, next_read);
+
+        code_context.patch_jump(end_of_file);
+        code_context.add(MOV.op1(register::RDI).op2(register::R13));
+        close(code_context);
+        code_context.add_slice(&[
+            MOV.op1(register::RAX).op2(register::RBX),
+            POP.op1(register::R13),
+            POP.op1(register::R12),
+            POP.op1(register::RBX),
+        ]);
+        code_context.patch_jump(end);
+    });
+}
+
+/// Replaces the contents of the file whose path is the string RAX points to
+/// with the string RSI points to. Puts the number of bytes written into RAX,
+/// or a negative error code.
+pub fn write_file(code_context: &mut CodeContext) {
+    code_context.call_routine("#write_file", |code_context| {
+        code_context.add(PUSH.op1(register::RSI));
+        open(code_context, "w");
+        code_context.add_slice(&[POP.op1(register::RSI), CMP.op1(register::RAX).op2(0_u32)]);
+        code_context.add(JL.op1(Operand::Offset32(0)));
+        let failed = code_context.get_pc() - 1;
+        code_context.add_slice(&[
+            PUSH.op1(register::RAX),
+            MOV.op1(register::RDI).op2(register::RAX),
+        ]);
+        write(code_context);
+        code_context.add_slice(&[POP.op1(register::RDI), PUSH.op1(register::RAX)]);
+        close(code_context);
+        code_context.add(POP.op1(register::RAX));
+        code_context.patch_jump(failed);
+    });
+}
+
+/// Builds a string known at compile time on the stack and returns the
+/// number of bytes pushed
+fn push_str(code_context: &mut CodeContext, s: &str) -> usize {
+    let words: Vec<u64> = s
+        .as_bytes()
+        .chunks(mem::size_of::<u64>())
+        .map(|chunk| {
+            chunk
+                .iter()
+                .rev()
+                .fold(0, |word, byte| (word << 8) | *byte as u64)
+        })
+        .collect();
+    for word in words.iter().rev() {
+        code_context.add_slice(&[MOV.op1(register::RAX).op2(*word), PUSH.op1(register::RAX)]);
+    }
+    code_context.add_slice(&[
+        MOV.op1(register::RAX).op2(s.len() as u64),
+        PUSH.op1(register::RAX),
+    ]);
+    (words.len() + 1) * mem::size_of::<u64>()
+}
+
+/// Prints a string known at compile time
+pub fn print_str(code_context: &mut CodeContext, stream: Stream, s: &str) {
+    let size = push_str(code_context, s);
+    code_context.add(MOV.op1(ARG_REGISTERS[0]).op2(register::RSP));
+    print(code_context, stream);
+    code_context.add(ADD.op1(register::RSP).op2(size as u32));
+}
+
+/// Flushes stdout, reports a runtime failure at the jc source `location`,
+/// as `file:line:column`, on stderr and exits with PANIC_EXIT_CODE. Without
+/// a `message` the string RSI points to is printed.
+pub fn panic(code_context: &mut CodeContext, location: &str, message: Option<&str>) {
+    if let Some(message) = message {
+        push_str(code_context, message);
+        code_context.add(MOV.op1(register::RSI).op2(register::RSP));
+    }
+    push_str(code_context, location);
+    code_context.add(MOV.op1(register::RDI).op2(register::RSP));
+    code_context.call_routine("#panic", |code_context| {
+        code_context.add_slice(&[PUSH.op1(register::RSI), PUSH.op1(register::RDI)]);
+        flush(code_context);
+        print_str(code_context, Stream::Stderr, "panic at ");
+        code_context.add(POP.op1(ARG_REGISTERS[0]));
+        print(code_context, Stream::Stderr);
+        print_str(code_context, Stream::Stderr, ": ");
+        code_context.add(POP.op1(ARG_REGISTERS[0]));
+        print(code_context, Stream::Stderr);
+        print_str(code_context, Stream::Stderr, "\n");
+        code_context.add(MOV.op1(register::RAX).op2(PANIC_EXIT_CODE));
+        exit(code_context, register::RAX);
+    });
+}
@@ -0,0 +1,100 @@
+use crate::emitter::text::{mnemonics::*, CodeContext};
+
+const LEAD_BYTES: [u32; 4] = [0x00, 0xC0, 0xE0, 0xF0];
+// Upper bound of the code points encoded with 1, 2 and 3 bytes
+const CODE_POINT_LIMITS: [u32; 3] = [0x80, 0x800, 0x10000];
+// Upper bound of the lead byte of sequences of 1, 2 and 3 bytes
+const LEAD_BYTE_LIMITS: [u32; 3] = [0x80, 0xE0, 0xF0];
+const LEAD_BYTE_MASKS: [u32; 4] = [0x7F, 0x1F, 0x0F, 0x07];
+const CONTINUATION_BYTE: u32 = 0x80;
+const CONTINUATION_MASK: u32 = 0x3F;
+const CONTINUATION_BITS: u8 = 6;
+
+/// Encodes the code point in `code_point` into the bytes of R8, first byte
+/// lowest, and puts the number of bytes into R9. Clobbers RAX.
+pub fn encode(code_context: &mut CodeContext, code_point: register::Register) {
+    let mut jumps_to_end = vec![];
+
+    for len in 1..=4 {
+        let skip = if len < 4 {
+            code_context.add(CMP.op1(code_point).op2(CODE_POINT_LIMITS[len - 1]));
+            code_context.add(JAE.op1(Operand::Offset32(0)));
+            Some(code_context.get_pc() - 1)
+        } else {
+            None
+        };
+
+        let lead_shift = CONTINUATION_BITS * (len as u8 - 1);
+        code_context.add(MOV.op1(register::R8).op2(code_point));
+        if lead_shift > 0 {
+            code_context.add_slice(&[
+                SHR.op1(register::R8).op2(lead_shift),
+                OR.op1(register::R8).op2(LEAD_BYTES[len - 1]),
+            ]);
+        }
+        for byte in 1..len {
+            let shift = CONTINUATION_BITS * (len - 1 - byte) as u8;
+            code_context.add(MOV.op1(register::RAX).op2(code_point));
+            if shift > 0 {
+                code_context.add(SHR.op1(register::RAX).op2(shift));
+            }
+            code_context.add_slice(&[
+                AND.op1(register::RAX).op2(CONTINUATION_MASK),
+                OR.op1(register::RAX).op2(CONTINUATION_BYTE),
+                SHL.op1(register::RAX).op2(8 * byte as u8),
+                OR.op1(register::R8).op2(register::RAX),
+            ]);
+        }
+        code_context.add(MOV.op1(register::R9).op2(len as u64));
+
+        if let Some(skip) = skip {
+            code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+            jumps_to_end.push(code_context.get_pc() - 1);
+            code_context.patch_jump(skip);
+        }
+    }
+
+    for jump in jumps_to_end {
+        code_context.patch_jump(jump);
+    }
+}
+
+/// Decodes the UTF-8 sequence `ptr` points to: the code point goes to RAX and
+/// the number of bytes it takes to RDX. Clobbers R8.
+pub fn decode(code_context: &mut CodeContext, ptr: register::Register) {
+    let mut jumps_to_end = vec![];
+
+    code_context.add(load_int(register::RAX, ptr, 0, 1, false));
+    for len in 1..=4 {
+        let skip = if len < 4 {
+            code_context.add(CMP.op1(register::RAX).op2(LEAD_BYTE_LIMITS[len - 1]));
+            code_context.add(JAE.op1(Operand::Offset32(0)));
+            Some(code_context.get_pc() - 1)
+        } else {
+            None
+        };
+
+        if len > 1 {
+            code_context.add(AND.op1(register::RAX).op2(LEAD_BYTE_MASKS[len - 1]));
+        }
+        for byte in 1..len {
+            code_context.add_slice(&[
+                SHL.op1(register::RAX).op2(CONTINUATION_BITS),
+                load_int(register::R8, ptr, byte as i32, 1, false),
+                AND.op1(register::R8).op2(CONTINUATION_MASK),
+                OR.op1(register::RAX).op2(register::R8),
+            ]);
+        }
+        code_context.add(MOV.op1(register::RDX).op2(len as u64));
+
+        if let Some(skip) = skip {
+            code_context.add(JMP_REL.op1(Operand::Offset32(0)));
+            jumps_to_end.push(code_context.get_pc() - 1);
+            code_context.patch_jump(skip);
+        }
+    }
+
+    for jump in jumps_to_end {
+        code_context.patch_jump(jump);
+    }
+}
@@ -3,6 +3,8 @@ use std::mem;
 use crate::emitter::stack::StackManager;
 use crate::emitter::{text::abi::windows::ARG_REGISTERS, text::mnemonics::*, text::CodeContext};
 
+use super::{utf8, IntFormat, Stream};
+
 #[derive(Debug, Clone, Copy)]
 pub enum StdHandle {
     Stdin = -10,
@@ -10,6 +12,15 @@ pub enum StdHandle {
     Stderr = -12,
 }
 
+impl From<Stream> for StdHandle {
+    fn from(stream: Stream) -> Self {
+        match stream {
+            Stream::Stdout => StdHandle::Stdout,
+            Stream::Stderr => StdHandle::Stderr,
+        }
+    }
+}
+
 fn _get_std_handle(code_context: &mut CodeContext, std_handle: StdHandle) {
     code_context.add_slice(&[
         MOV.op1(ARG_REGISTERS[0]).op2(std_handle as u64),
@@ -95,12 +106,12 @@ pub fn itoa(code_context: &mut CodeContext) {
     ]);
 }
 
-pub fn print(code_context: &mut CodeContext) {
+pub fn print(code_context: &mut CodeContext, stream: Stream) {
     code_context.add_slice(&[
         PUSH.op1(ARG_REGISTERS[0]),
         SUB.op1(register::RSP).op2(8_u32),
     ]);
-    _get_std_handle(code_context, StdHandle::Stdout);
+    _get_std_handle(code_context, stream.into());
     code_context.add_slice(&[
         ADD.op1(register::RSP).op2(8_u32),
         POP.op1(ARG_REGISTERS[0]),
@@ -109,7 +120,7 @@ pub fn print(code_context: &mut CodeContext) {
     _print(code_context);
 }
 
-pub fn printd(code_context: &mut CodeContext) {
+pub fn printd(code_context: &mut CodeContext, stream: Stream) {
     itoa(code_context);
     code_context.add_slice(&[
         MOV.op1(register::RSP).op2(register::RAX),
@@ -119,7 +130,7 @@ pub fn printd(code_context: &mut CodeContext) {
         PUSH.op1(ARG_REGISTERS[0]),
     ]);
 
-    _get_std_handle(code_context, StdHandle::Stdout);
+    _get_std_handle(code_context, stream.into());
 
     code_context.add_slice(&[
         POP.op1(ARG_REGISTERS[0]),
@@ -140,10 +151,200 @@ pub fn printd(code_context: &mut CodeContext) {
     ]);
 }
 
-pub fn exit(code_context: &mut CodeContext, exit_code: u64) {
+/// Prints the signed integer in the first argument register
+pub fn printi(code_context: &mut CodeContext, stream: Stream) {
+    let mut minus = CodeContext::new(0);
+    minus.add_slice(&[
+        PUSH.op1(ARG_REGISTERS[0]),
+        PUSH.op1(0x2D_u32), // ascii code for '-'
+        PUSH.op1(1_u32),    // string length
+        MOV.op1(ARG_REGISTERS[0]).op2(register::RSP),
+    ]);
+    print(&mut minus, stream);
+    minus.add_slice(&[
+        ADD.op1(register::RSP).op2(16_u32),
+        POP.op1(ARG_REGISTERS[0]),
+        NEG.op1(ARG_REGISTERS[0]),
+    ]);
+
+    code_context.add(CMP.op1(ARG_REGISTERS[0]).op2(0_u32));
+    code_context.add(JGE.op1(Operand::Offset32(minus.get_code_size() as i32)));
+    code_context.append(&minus);
+
+    printd(code_context, stream);
+}
+
+/// Prints the char whose code point is in the first argument register
+pub fn print_char(code_context: &mut CodeContext, stream: Stream) {
+    utf8::encode(code_context, ARG_REGISTERS[0]);
+    code_context.add_slice(&[
+        PUSH.op1(register::R8),
+        PUSH.op1(register::R9), // string length
+        MOV.op1(ARG_REGISTERS[0]).op2(register::RSP),
+    ]);
+    print(code_context, stream);
+    code_context.add(ADD.op1(register::RSP).op2(16_u32));
+}
+
+/// Builds a string known at compile time on the stack and returns the
+/// number of bytes pushed
+fn _push_str(code_context: &mut CodeContext, s: &str) -> usize {
+    let words: Vec<u64> = s
+        .as_bytes()
+        .chunks(mem::size_of::<u64>())
+        .map(|chunk| {
+            chunk
+                .iter()
+                .rev()
+                .fold(0, |word, byte| (word << 8) | *byte as u64)
+        })
+        .collect();
+    for word in words.iter().rev() {
+        code_context.add_slice(&[MOV.op1(register::RAX).op2(*word), PUSH.op1(register::RAX)]);
+    }
+    code_context.add_slice(&[
+        MOV.op1(register::RAX).op2(s.len() as u64),
+        PUSH.op1(register::RAX),
+    ]);
+    (words.len() + 1) * mem::size_of::<u64>()
+}
+
+/// Prints a string known at compile time. Callers don't align the stack, it
+/// is aligned here for the calls into kernel32.
+pub fn print_str(code_context: &mut CodeContext, stream: Stream, s: &str) {
+    code_context.add_slice(&[
+        MOV.op1(register::R10).op2(register::RSP),
+        MOV.op1(register::R9).op2(-16_i64 as u64),
+        AND.op1(register::RSP).op2(register::R9),
+        PUSH.op1(register::R10), // push address before alignment
+    ]);
+    // Length and words plus the saved address make a multiple of 16
+    let words = s.len().div_ceil(mem::size_of::<u64>());
+    let padding = if words.is_multiple_of(2) {
+        0
+    } else {
+        mem::size_of::<u64>()
+    };
+    code_context.add(SUB.op1(register::RSP).op2(padding as u32));
+    let size = _push_str(code_context, s);
+    code_context.add(MOV.op1(ARG_REGISTERS[0]).op2(register::RSP));
+    print(code_context, stream);
+    code_context.add_slice(&[
+        ADD.op1(register::RSP).op2((size + padding) as u32),
+        POP.op1(register::R9), // pop address before alignment
+        MOV.op1(register::RSP).op2(register::R9),
+    ]);
+}
+
+/// The routines below have no Windows implementation, the type checker
+/// rejects the programs that use them on Windows
+fn _unsupported(feature: &str) -> ! {
+    unreachable!("{feature} on Windows")
+}
+
+/// Prints the integer in the first argument register laid out as `format`
+pub fn print_formatted_int(_code_context: &mut CodeContext, _stream: Stream, _format: IntFormat) {
+    _unsupported("formatted integers")
+}
+
+/// Output goes straight to the console on Windows, stdout is not buffered
+pub fn buffer_stdout(_code_context: &mut CodeContext) {}
+
+/// Writes out the bytes buffered for stdout, there are none on Windows
+pub fn flush(_code_context: &mut CodeContext) {}
+
+/// Exits with the status held in `exit_code`
+pub fn exit(code_context: &mut CodeContext, exit_code: register::Register) {
     code_context.add_slice(&[
         MOV.op1(register::RAX).op2(exit_code),
         CALL.op1(Operand::Offset32(0))
             .symbol("ExitProcess".to_string()),
     ]);
 }
+
+/// Copies the NUL terminated string RSI points to into a new string and puts
+/// its address into RAX
+pub fn from_c_string(_code_context: &mut CodeContext) {
+    _unsupported("strings returned by C functions")
+}
+
+/// Copies the string RAX points to into a new string and puts its address
+/// into RAX
+pub fn copy_string(_code_context: &mut CodeContext) {
+    _unsupported("returning strings")
+}
+
+/// Puts the number of command-line arguments into RAX
+pub fn arg_count(_code_context: &mut CodeContext) {
+    _unsupported("command-line arguments")
+}
+
+/// Copies the command-line argument at the index in RAX into a new string
+/// and puts its address into RAX
+pub fn arg(_code_context: &mut CodeContext) {
+    _unsupported("command-line arguments")
+}
+
+/// Looks up the environment variable named by the string RAX points to and
+/// puts the address of a copy of its value into RAX
+pub fn env(_code_context: &mut CodeContext) {
+    _unsupported("environment variables")
+}
+
+/// Reads a line from stdin into a new string and puts its address into RAX
+pub fn read_line(_code_context: &mut CodeContext) {
+    _unsupported("reading stdin")
+}
+
+/// Reads a decimal integer from stdin into RAX
+pub fn read_int(_code_context: &mut CodeContext) {
+    _unsupported("reading stdin")
+}
+
+/// Reads at most RDX bytes of stdin into the string RDI points to and puts
+/// the number of bytes read into RAX
+pub fn read(_code_context: &mut CodeContext) {
+    _unsupported("reading stdin")
+}
+
+/// Opens the file whose path is the string RAX points to and puts the file
+/// handle into RAX
+pub fn open(_code_context: &mut CodeContext, _mode: &str) {
+    _unsupported("file I/O")
+}
+
+/// Closes the file handle in the first argument register
+pub fn close(_code_context: &mut CodeContext) {
+    _unsupported("file I/O")
+}
+
+/// Writes the string in the second argument register to the file handle in
+/// the first one
+pub fn write(_code_context: &mut CodeContext) {
+    _unsupported("file I/O")
+}
+
+/// Reads at most RDX bytes from a file handle into a string
+pub fn read_fd(_code_context: &mut CodeContext) {
+    _unsupported("file I/O")
+}
+
+/// Reads the file whose path is the string RAX points to into a new string
+pub fn read_file(_code_context: &mut CodeContext) {
+    _unsupported("file I/O")
+}
+
+/// Puts the error code of the last read_file into RAX
+pub fn file_error(_code_context: &mut CodeContext) {
+    _unsupported("file I/O")
+}
+
+/// Replaces the contents of the file whose path is the string RAX points to
+pub fn write_file(_code_context: &mut CodeContext) {
+    _unsupported("file I/O")
+}
+
+/// Reports a runtime failure at the jc source `location` on stderr and exits
+pub fn panic(_code_context: &mut CodeContext, _location: &str, _message: Option<&str>) {
+    _unsupported("panicking")
+}
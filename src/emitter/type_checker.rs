@@ -0,0 +1,1090 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    ast, const_eval,
+    resolver::{Bindings, SymbolId, SymbolKind},
+    text::{abi, format},
+    Emit,
+};
+use crate::diagnostics::Diagnostic;
+
+/// Type of every expression of a checked program, keyed by the address of
+/// the expression in the AST
+#[derive(Debug, Clone, Default)]
+pub struct Types {
+    types: HashMap<*const ast::Expression, ast::Type>,
+}
+
+impl Types {
+    pub fn get(&self, expr: &ast::Expression) -> &ast::Type {
+        self.types
+            .get(&(expr as *const _))
+            .unwrap_or_else(|| panic!("expression was not type checked: {expr:?}"))
+    }
+
+    /// Integer type the expression is computed with, chars are their u32
+    /// code point
+    pub fn int_type(&self, expr: &ast::Expression) -> Option<ast::IntType> {
+        let _type = self.get(expr);
+        match _type.name {
+            _ if !_type.modifiers.is_empty() => None,
+            ast::TypeName::Int(int_type) => Some(int_type),
+            ast::TypeName::Char => Some(ast::IntType::U32),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, expr: &ast::Expression, _type: ast::Type) {
+        self.types.insert(expr as *const _, _type);
+    }
+}
+
+/// Checks the whole program, built as `emit`, and annotates every
+/// expression with its type. All errors are collected so they can be
+/// reported at once.
+pub fn check(
+    block: &ast::Block,
+    bindings: &Bindings,
+    emit: Emit,
+) -> Result<Types, Vec<Diagnostic>> {
+    let mut type_checker = TypeChecker {
+        bindings: bindings.clone(),
+        emit,
+        ..Default::default()
+    };
+    type_checker.visit_ast(block);
+    if type_checker.errors.is_empty() {
+        Ok(type_checker.types)
+    } else {
+        Err(type_checker.errors)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    args: Vec<ast::Arg>,
+    return_type: ast::Type,
+}
+
+/// Parameter of a builtin function
+#[derive(Debug, Clone, Copy)]
+enum Param {
+    Int,
+    /// A string or a reference to one
+    String,
+}
+
+/// Parameters and return type of the builtins with a fixed signature
+fn builtin(name: &str) -> Option<(&'static [Param], ast::TypeName)> {
+    use ast::{IntType::*, TypeName};
+    use Param::*;
+
+    let builtin: (&[Param], TypeName) = match name {
+        "flush" => (&[], TypeName::Unit),
+        "exit" => (&[Int], TypeName::Unit),
+        "assert" => (&[Int], TypeName::Unit),
+        "panic" => (&[String], TypeName::Unit),
+        "len" => (&[String], TypeName::Int(U64)),
+        "arg_count" => (&[], TypeName::Int(I64)),
+        "arg" => (&[Int], TypeName::String),
+        "env" => (&[String], TypeName::String),
+        "read_int" => (&[], TypeName::Int(I64)),
+        "read_line" => (&[], TypeName::String),
+        "read_file" => (&[String], TypeName::String),
+        "file_error" => (&[], TypeName::Int(I64)),
+        "open" => (&[String, String], TypeName::Int(I64)),
+        "close" => (&[Int], TypeName::Int(I64)),
+        "write" => (&[Int, String], TypeName::Int(I64)),
+        "write_file" => (&[String, String], TypeName::Int(I64)),
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+/// Functions provided by the compiler rather than declared in the program
+pub fn is_builtin(name: &str) -> bool {
+    builtin(name).is_some() || matches!(name, "print" | "eprint" | "println" | "eprintln" | "read")
+}
+
+/// Builtins whose runtime routines only exist on Linux
+const LINUX_BUILTINS: &[&str] = &[
+    "assert",
+    "panic",
+    "arg_count",
+    "arg",
+    "env",
+    "read_line",
+    "read_int",
+    "read",
+    "open",
+    "close",
+    "write",
+    "read_file",
+    "file_error",
+    "write_file",
+];
+
+fn plain(name: ast::TypeName) -> ast::Type {
+    ast::Type::new(name, vec![])
+}
+
+fn is_int(_type: &ast::Type) -> bool {
+    _type.modifiers.is_empty() && matches!(_type.name, ast::TypeName::Int(_))
+}
+
+/// Whether every path through `block` ends with a return
+fn returns(block: &ast::Block) -> bool {
+    block.stmts.iter().any(|stmt| match stmt {
+        ast::Statement::ControlFlow(ast::ControlFlow::Return(_)) => true,
+        ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => {
+            returns(&_if.then) && _if.otherwise.as_ref().is_some_and(returns)
+        }
+        ast::Statement::Block(block) => returns(block),
+        _ => false,
+    })
+}
+
+/// Expressions made of integer literals only, their type comes from where
+/// they are used
+fn is_untyped(expr: &ast::Expression) -> bool {
+    match expr {
+        ast::Expression::Literal(ast::Literal::Integer(_), _) => true,
+        ast::Expression::Unary(ast::UnaryOperation::Minus(expr), _) => is_untyped(expr),
+        ast::Expression::Binary(binary) if !binary.is_comparison() => {
+            let (lhs, rhs) = binary.operands();
+            is_untyped(lhs) && is_untyped(rhs)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Default)]
+struct TypeChecker {
+    types: Types,
+    errors: Vec<Diagnostic>,
+    bindings: Bindings,
+    // Type of each variable, `None` once an error made it unknown
+    variables: HashMap<SymbolId, Option<ast::Type>>,
+    functions: HashMap<String, Signature>,
+    // Value of every int const known at compile time
+    consts: HashMap<SymbolId, (i64, ast::IntType)>,
+    // String variables declared with a literal hold its bytes, the others
+    // point to a string computed at runtime
+    inline_strings: HashSet<SymbolId>,
+    // String consts, laid out in the data section
+    const_strings: HashSet<SymbolId>,
+    emit: Emit,
+    // Name of the function being checked
+    function: Option<ast::Ident>,
+    // Statement being checked
+    span: Option<ast::Span>,
+}
+
+impl TypeChecker {
+    fn visit_ast(&mut self, block: &ast::Block) {
+        // Signatures first, calls can precede the callee declaration
+        for stmt in &block.stmts {
+            let (name, args, return_type) = match stmt {
+                ast::Statement::FuncDeclaration(func_decl) => {
+                    (&func_decl.name, &func_decl.args, &func_decl.return_type)
+                }
+                ast::Statement::ExternFuncDeclaration(extern_decl) => (
+                    &extern_decl.name,
+                    &extern_decl.args,
+                    &extern_decl.return_type,
+                ),
+                _ => continue,
+            };
+            // Arguments are only passed in registers
+            if let Some(arg) = args.get(abi::ARG_REGISTERS.len()) {
+                self.error(
+                    "E0206",
+                    format!(
+                        "Function {} takes {} arguments, at most {} are supported",
+                        name.value,
+                        args.len(),
+                        abi::ARG_REGISTERS.len()
+                    ),
+                    &arg.name.span,
+                    "too many arguments",
+                );
+            }
+            if matches!(stmt, ast::Statement::ExternFuncDeclaration(_))
+                && return_type.name == ast::TypeName::String
+            {
+                self.linux_only("Returning strings from C", &name.span);
+            }
+            // Values are returned in RAX, there is no frame left to point to
+            if !return_type.modifiers.is_empty() {
+                self.error(
+                    "E0206",
+                    format!("Function {} cannot return a reference", name.value),
+                    &name.span,
+                    format!("returns {return_type}"),
+                );
+            }
+            let signature = Signature {
+                args: args.clone(),
+                return_type: return_type.clone(),
+            };
+            self.functions.insert(name.value.clone(), signature);
+        }
+        if self.emit == Emit::Exe {
+            self.visit_main(block);
+        }
+        self.visit_block(block);
+    }
+
+    /// Executables start at `main`, its return value is the exit status
+    fn visit_main(&mut self, block: &ast::Block) {
+        let main = block.stmts.iter().find_map(|stmt| match stmt {
+            ast::Statement::FuncDeclaration(func_decl) if func_decl.name.value == "main" => {
+                Some(func_decl)
+            }
+            _ => None,
+        });
+        let Some(main) = main else {
+            let error = Diagnostic::error("E0206", "Executables need a function main")
+                .with_help("declare `func main()`, or build an object file with --emit obj");
+            self.errors.push(error);
+            return;
+        };
+        if let Some(arg) = main.args.first() {
+            let error = Diagnostic::error(
+                "E0202",
+                format!(
+                    "Function main takes no arguments, found {}",
+                    main.args.len()
+                ),
+            )
+            .with_primary(&arg.name.span, "unexpected argument")
+            .with_help("read command-line arguments with arg_count and arg");
+            self.errors.push(error);
+        }
+        let return_type = &main.return_type;
+        if !matches!(
+            return_type.name,
+            ast::TypeName::Unit | ast::TypeName::Int(_)
+        ) {
+            self.error(
+                "E0203",
+                format!("Function main must return an integer or nothing, found {return_type}"),
+                &main.name.span,
+                format!("returns {return_type}"),
+            );
+        }
+    }
+
+    /// Reports `feature` when building for Windows, its runtime lacks it
+    fn linux_only(&mut self, feature: &str, span: &ast::Span) {
+        if cfg!(target_os = "windows") {
+            self.error(
+                "E0206",
+                format!("{feature} is not supported on Windows"),
+                span,
+                "only available on Linux",
+            );
+        }
+    }
+
+    /// Reports an error about the code found at `span`
+    fn error(&mut self, code: &str, message: String, span: &ast::Span, label: impl Into<String>) {
+        let error = Diagnostic::error(code, message).with_primary(span, label);
+        self.errors.push(error);
+    }
+
+    fn statement_span(&self) -> ast::Span {
+        self.span.clone().expect("no statement is being checked")
+    }
+
+    fn visit_block(&mut self, block: &ast::Block) {
+        for (stmt, span) in block.stmts.iter().zip(&block.spans) {
+            self.span = Some(span.clone());
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &ast::Statement) {
+        match statement {
+            ast::Statement::VarDeclaration(var_decl) => self.visit_var_declaration(var_decl),
+            ast::Statement::FuncDeclaration(func_decl) => self.visit_func_declaration(func_decl),
+            ast::Statement::ExternFuncDeclaration(_) => (),
+            ast::Statement::Loop(l) => self.visit_loop(l),
+            ast::Statement::Assignment(assign) => self.visit_assignment(assign),
+            ast::Statement::Expression(expr) => {
+                self.visit_expression(expr, None);
+            }
+            ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => {
+                self.visit_condition(&_if.condition);
+                self.visit_block(&_if.then);
+                if let Some(otherwise) = &_if.otherwise {
+                    self.visit_block(otherwise);
+                }
+            }
+            ast::Statement::ControlFlow(ast::ControlFlow::Return(expr)) => {
+                self.visit_return(expr.as_ref())
+            }
+            ast::Statement::Block(block) => self.visit_block(block),
+            ast::Statement::Error => (),
+        }
+    }
+
+    fn visit_var_declaration(&mut self, var_decl: &ast::VarDeclaration) {
+        let name = &var_decl.name.value;
+        let ast::RhsExpression::Expression(expr) = &var_decl.rhs else {
+            self.error(
+                "E0206",
+                format!("Block expressions are not supported: {name}"),
+                &var_decl.name.span,
+                "",
+            );
+            self.declare(&var_decl.name, var_decl._type.clone());
+            return;
+        };
+
+        let errors = self.errors.len();
+        let found = self.visit_expression(expr, var_decl._type.as_ref());
+        if let ast::Expression::Unary(ast::UnaryOperation::Ref(_), _) = expr {
+            self.error(
+                "E0206",
+                format!("Variables cannot hold references: {name}"),
+                &expr.span(),
+                "pass the reference to a function instead",
+            );
+        }
+        let _type = match (found, &var_decl._type) {
+            (Some(found), Some(declared)) => {
+                if !self.assignable(expr, &found, declared) {
+                    let error = Diagnostic::error(
+                        "E0201",
+                        format!("Cannot assign {found} to {declared}: {name}"),
+                    )
+                    .with_primary(&expr.span(), format!("expected {declared}, found {found}"))
+                    .with_secondary(&var_decl.name.span, format!("declared as {declared}"));
+                    self.errors.push(error);
+                }
+                Some(declared.clone())
+            }
+            (Some(found), None) if found.name == ast::TypeName::Unit => {
+                self.error(
+                    "E0201",
+                    format!("Cannot declare {name} with a value of type {found}"),
+                    &expr.span(),
+                    "this does not produce a value",
+                );
+                None
+            }
+            (found, declared) => declared.clone().or(found),
+        };
+        if var_decl.declarion_type == ast::VarDeclarationType::Const && self.errors.len() == errors
+        {
+            self.visit_const(var_decl, expr, _type.as_ref());
+        }
+        if matches!(expr, ast::Expression::Literal(ast::Literal::String(_), _)) {
+            let id = self.bindings.id(&var_decl.name);
+            match var_decl.declarion_type {
+                ast::VarDeclarationType::Let => self.inline_strings.insert(id),
+                ast::VarDeclarationType::Const => self.const_strings.insert(id),
+            };
+        }
+        self.declare(&var_decl.name, _type);
+    }
+
+    /// Consts are laid out in the data section, their value must be known
+    /// at compile time
+    fn visit_const(
+        &mut self,
+        var_decl: &ast::VarDeclaration,
+        expr: &ast::Expression,
+        _type: Option<&ast::Type>,
+    ) {
+        let name = &var_decl.name.value;
+        match _type {
+            Some(ast::Type {
+                name: ast::TypeName::Int(int_type),
+                modifiers,
+            }) if modifiers.is_empty() => match self.eval(expr, *int_type) {
+                Ok(Some(value)) => {
+                    self.consts
+                        .insert(self.bindings.id(&var_decl.name), (value, *int_type));
+                }
+                Ok(None) => self.error(
+                    "E0206",
+                    format!("const {name} must be known at compile time"),
+                    &expr.span(),
+                    "computed at runtime",
+                ),
+                Err(error) => self.errors.push(*error),
+            },
+            Some(_type) if !matches!(expr, ast::Expression::Literal(..)) => self.error(
+                "E0206",
+                format!("const {name} of type {_type} must be a literal"),
+                &expr.span(),
+                "not a literal",
+            ),
+            _ => (),
+        }
+    }
+
+    fn visit_assignment(&mut self, assign: &ast::Assignment) {
+        let name = &assign.variable_name.value;
+        let ast::RhsExpression::Expression(expr) = &assign.rhs else {
+            self.error(
+                "E0206",
+                format!("Block expressions are not supported: {name}"),
+                &assign.variable_name.span,
+                "",
+            );
+            return;
+        };
+
+        // References are assigned through
+        let target = self
+            .lookup(&assign.variable_name)
+            .map(|_type| plain(_type.name));
+        let found = self.visit_expression(expr, target.as_ref());
+        let declaration = &self.bindings.get(&assign.variable_name).span;
+        let Some((found, target)) = found.zip(target) else {
+            return;
+        };
+        if !self.assignable(expr, &found, &target) {
+            let error = Diagnostic::error(
+                "E0201",
+                format!("Cannot assign {found} to {target}: {name}"),
+            )
+            .with_primary(&expr.span(), format!("expected {target}, found {found}"))
+            .with_secondary(declaration, format!("declared as {target} here"));
+            self.errors.push(error);
+            return;
+        }
+
+        if target.name == ast::TypeName::String {
+            let inline = self
+                .inline_strings
+                .contains(&self.bindings.id(&assign.variable_name));
+            let literal = matches!(expr, ast::Expression::Literal(..));
+            let error = match (inline, literal) {
+                (true, false) => Diagnostic::error(
+                    "E0206",
+                    format!("Cannot assign a computed string to {name}"),
+                )
+                .with_primary(&expr.span(), "computed at runtime")
+                .with_secondary(declaration, "declared with a literal here"),
+                (false, true) => Diagnostic::error(
+                    "E0206",
+                    format!("Cannot assign a literal to {name}, it points to a computed string"),
+                )
+                .with_primary(&expr.span(), "string literal")
+                .with_secondary(declaration, "declared with a computed string here"),
+                _ => return,
+            };
+            self.errors.push(error);
+        }
+    }
+
+    fn visit_func_declaration(&mut self, func_decl: &ast::FuncDeclaration) {
+        for arg in &func_decl.args {
+            self.declare(&arg.name, Some(arg._type.clone()));
+        }
+
+        let function = self.function.replace(func_decl.name.clone());
+        self.visit_block(&func_decl.body);
+        self.function = function;
+
+        let return_type = &func_decl.return_type;
+        if return_type.name != ast::TypeName::Unit && !returns(&func_decl.body) {
+            let error = Diagnostic::error(
+                "E0203",
+                format!(
+                    "Function {} may end without returning {return_type}",
+                    func_decl.name.value
+                ),
+            )
+            .with_primary(&func_decl.name.span, format!("returns {return_type}"))
+            .with_help("end every path of the body with a return");
+            self.errors.push(error);
+        }
+    }
+
+    /// Integers convert implicitly to integer types at least as wide, and to
+    /// narrower ones when their value is known to fit. Anything else must
+    /// match exactly
+    fn assignable(&self, expr: &ast::Expression, found: &ast::Type, expected: &ast::Type) -> bool {
+        match (&found.name, &expected.name) {
+            _ if found == expected => true,
+            (ast::TypeName::Int(from), ast::TypeName::Int(to))
+                if is_int(found) && is_int(expected) =>
+            {
+                from.size() <= to.size() || matches!(self.eval(expr, *to), Ok(Some(_)))
+            }
+            _ => false,
+        }
+    }
+
+    fn visit_loop(&mut self, l: &ast::Loop) {
+        let var_type = match &l.iter {
+            ast::LoopIter::Range { start, end } => {
+                let int = plain(ast::TypeName::Int(ast::IntType::I64));
+                for bound in [start, end] {
+                    match self.visit_expression(bound, Some(&int)) {
+                        Some(found) if !is_int(&found) => self.error(
+                            "E0201",
+                            format!("Loop bounds must be integers, found {found}"),
+                            &bound.span(),
+                            "expected an integer",
+                        ),
+                        Some(_) if matches!(self.eval(bound, ast::IntType::I64), Ok(None)) => self
+                            .error(
+                                "E0206",
+                                "Loop bounds must be known at compile time".to_string(),
+                                &bound.span(),
+                                "computed at runtime",
+                            ),
+                        _ => (),
+                    }
+                }
+                Some(int)
+            }
+            ast::LoopIter::Collection(collection) => {
+                match self.visit_expression(collection, None) {
+                    Some(found) if found.name != ast::TypeName::String => self.error(
+                        "E0201",
+                        format!("Only strings can be iterated, found {found}"),
+                        &collection.span(),
+                        "expected a string",
+                    ),
+                    // The string is read again on every iteration
+                    Some(_)
+                        if !matches!(
+                            collection,
+                            ast::Expression::Ident(_)
+                                | ast::Expression::Unary(ast::UnaryOperation::Ref(_), _)
+                                | ast::Expression::Literal(ast::Literal::String(_), _)
+                        ) =>
+                    {
+                        let error = Diagnostic::error(
+                            "E0206",
+                            "Only string variables and literals can be iterated",
+                        )
+                        .with_primary(&collection.span(), "computed at runtime")
+                        .with_help("store the string in a variable first");
+                        self.errors.push(error);
+                    }
+                    _ => (),
+                }
+                Some(plain(ast::TypeName::Char))
+            }
+        };
+        self.declare(&l.var, var_type);
+        self.visit_block(&l.body);
+    }
+
+    fn visit_condition(&mut self, condition: &ast::Expression) {
+        match self.visit_expression(condition, None) {
+            Some(found) if !is_int(&found) => self.error(
+                "E0201",
+                format!("Condition must be an integer, found {found}"),
+                &condition.span(),
+                "expected an integer",
+            ),
+            _ => (),
+        }
+    }
+
+    fn visit_return(&mut self, expr: Option<&ast::Expression>) {
+        let Some(function) = self.function.clone() else {
+            let span = self.statement_span();
+            self.error(
+                "E0206",
+                "return outside of a function".to_string(),
+                &span,
+                "",
+            );
+            return;
+        };
+        let name = &function.value;
+        let return_type = self.functions[name].return_type.clone();
+
+        match (expr, &return_type.name) {
+            (Some(expr), ast::TypeName::Unit) => {
+                self.visit_expression(expr, None);
+                let error =
+                    Diagnostic::error("E0203", format!("Function {name} does not return a value"))
+                        .with_primary(&expr.span(), "unexpected return value")
+                        .with_secondary(&function.span, "declared without a return type");
+                self.errors.push(error);
+            }
+            (Some(expr), _) => {
+                // Strings of the frame are copied out to the heap
+                if let ast::Expression::Ident(id) = expr {
+                    if self.inline_strings.contains(&self.bindings.id(id)) {
+                        self.linux_only("Returning a local string", &expr.span());
+                    }
+                }
+                let found = self.visit_expression(expr, Some(&return_type));
+                if let Some(found) =
+                    found.filter(|found| !self.assignable(expr, found, &return_type))
+                {
+                    let error = Diagnostic::error(
+                        "E0201",
+                        format!("Function {name} returns {return_type}, found {found}"),
+                    )
+                    .with_primary(&expr.span(), format!("expected {return_type}"))
+                    .with_secondary(&function.span, format!("returns {return_type}"));
+                    self.errors.push(error);
+                }
+            }
+            (None, ast::TypeName::Unit) => (),
+            (None, _) => {
+                let error = Diagnostic::error(
+                    "E0203",
+                    format!("Function {name} must return {return_type}"),
+                )
+                .with_primary(&self.statement_span(), "return without a value")
+                .with_secondary(&function.span, format!("returns {return_type}"));
+                self.errors.push(error);
+            }
+        }
+    }
+
+    /// Checks `expr` and records its type. Integer literals take the integer
+    /// type `expected` hints at. `None` when an error was reported.
+    fn visit_expression(
+        &mut self,
+        expr: &ast::Expression,
+        expected: Option<&ast::Type>,
+    ) -> Option<ast::Type> {
+        let errors = self.errors.len();
+        let _type = match expr {
+            ast::Expression::Literal(ast::Literal::Integer(n), span) => {
+                self.visit_integer(n.value.into(), expected, span)
+            }
+            ast::Expression::Literal(ast::Literal::String(_), _) => plain(ast::TypeName::String),
+            ast::Expression::Literal(ast::Literal::Char(_), _) => plain(ast::TypeName::Char),
+            // References are read through
+            ast::Expression::Ident(id) => plain(self.lookup(id)?.name),
+            ast::Expression::Unary(ast::UnaryOperation::Ref(inner), _) => {
+                let ast::Expression::Ident(id) = inner.as_ref() else {
+                    self.visit_expression(inner, None);
+                    self.error(
+                        "E0206",
+                        "Only variables can be referenced".to_string(),
+                        &inner.span(),
+                        "not a variable",
+                    );
+                    return None;
+                };
+                let _type = self.lookup(id)?;
+                self.types.insert(inner, plain(_type.name.clone()));
+                ast::Type::new(_type.name, vec![ast::TypeModifer::Ref])
+            }
+            ast::Expression::Unary(
+                ast::UnaryOperation::Minus(inner) | ast::UnaryOperation::Not(inner),
+                _,
+            ) => {
+                let _type = match inner.as_ref() {
+                    // A negated literal must fit, not the literal itself
+                    ast::Expression::Literal(ast::Literal::Integer(n), _)
+                        if matches!(
+                            expr,
+                            ast::Expression::Unary(ast::UnaryOperation::Minus(_), _)
+                        ) =>
+                    {
+                        let _type =
+                            self.visit_integer(-i128::from(n.value), expected, &expr.span());
+                        self.types.insert(inner, _type.clone());
+                        _type
+                    }
+                    _ => self.visit_expression(inner, expected)?,
+                };
+                if !is_int(&_type) {
+                    self.error(
+                        "E0201",
+                        format!("Expected an integer, found {_type}"),
+                        &inner.span(),
+                        "expected an integer",
+                    );
+                    return None;
+                }
+                _type
+            }
+            ast::Expression::Binary(binary) => self.visit_binary(binary, expected)?,
+            ast::Expression::Index(index) => {
+                // Out of bounds indices panic
+                self.linux_only("Indexing strings", &index.span);
+                let u64 = plain(ast::TypeName::Int(ast::IntType::U64));
+                let value = self.visit_expression(&index.value, None);
+                let position = self.visit_expression(&index.index, Some(&u64));
+                match value {
+                    Some(value) if value.name != ast::TypeName::String => self.error(
+                        "E0201",
+                        format!("Only strings can be indexed, found {value}"),
+                        &index.value.span(),
+                        "expected a string",
+                    ),
+                    _ => (),
+                }
+                match position {
+                    Some(position) if !is_int(&position) => self.error(
+                        "E0201",
+                        format!("Index must be an integer, found {position}"),
+                        &index.index.span(),
+                        "expected an integer",
+                    ),
+                    _ => (),
+                }
+                plain(ast::TypeName::Int(ast::IntType::U8))
+            }
+            ast::Expression::Call(call) => self.visit_call(call)?,
+        };
+
+        // Code generation folds constant expressions, their overflows are
+        // reported here once, at the innermost expression at fault
+        let folded = matches!(
+            expr,
+            ast::Expression::Binary(_) | ast::Expression::Unary(ast::UnaryOperation::Minus(_), _)
+        );
+        if let (true, ast::TypeName::Int(int_type)) = (folded, &_type.name) {
+            if self.errors.len() == errors {
+                if let Err(error) = self.eval(expr, *int_type) {
+                    self.errors.push(*error);
+                }
+            }
+        }
+
+        self.types.insert(expr, _type.clone());
+        Some(_type)
+    }
+
+    fn eval(
+        &self,
+        expr: &ast::Expression,
+        int_type: ast::IntType,
+    ) -> Result<Option<i64>, Box<Diagnostic>> {
+        let lookup = |id: &ast::Ident| self.consts.get(&self.bindings.id(id)).copied();
+        const_eval::eval_int_with(&lookup, expr, int_type)
+    }
+
+    fn visit_integer(
+        &mut self,
+        value: i128,
+        expected: Option<&ast::Type>,
+        span: &ast::Span,
+    ) -> ast::Type {
+        match expected.map(|expected| &expected.name) {
+            Some(ast::TypeName::Int(int_type)) => {
+                if !int_type.fits(value) {
+                    let error = Diagnostic::error(
+                        "E0204",
+                        format!("literal {value} does not fit in {int_type}"),
+                    )
+                    .with_primary(span, format!("out of range for {int_type}"));
+                    self.errors.push(error);
+                }
+                plain(ast::TypeName::Int(*int_type))
+            }
+            _ => plain(ast::TypeName::Int(ast::IntType::I64)),
+        }
+    }
+
+    fn visit_binary(
+        &mut self,
+        binary: &ast::BinaryOperation,
+        expected: Option<&ast::Type>,
+    ) -> Option<ast::Type> {
+        let (lhs, rhs) = binary.operands();
+        // Literals take the type of the other operand
+        let (first, second) = if is_untyped(lhs) && !is_untyped(rhs) {
+            (rhs, lhs)
+        } else {
+            (lhs, rhs)
+        };
+        let expected = expected.filter(|expected| is_int(expected) && !binary.is_comparison());
+        let first_type = self.visit_expression(first, expected);
+        let second_type = self.visit_expression(second, first_type.as_ref().or(expected));
+        let (first_type, second_type) = (first_type?, second_type?);
+        let (lhs_type, rhs_type) = if std::ptr::eq(first, lhs) {
+            (&first_type, &second_type)
+        } else {
+            (&second_type, &first_type)
+        };
+
+        // Chars compare with chars, integers of any type with each other
+        let valid = (is_int(lhs_type) && is_int(rhs_type))
+            || (binary.is_comparison()
+                && lhs_type == rhs_type
+                && lhs_type.name == ast::TypeName::Char);
+        if !valid {
+            let error = Diagnostic::error(
+                "E0201",
+                format!("Cannot apply {binary} to {lhs_type} and {rhs_type}"),
+            )
+            .with_primary(&lhs.span().to(&rhs.span()), "")
+            .with_secondary(&lhs.span(), lhs_type.to_string())
+            .with_secondary(&rhs.span(), rhs_type.to_string());
+            self.errors.push(error);
+            return None;
+        }
+
+        if binary.is_comparison() {
+            // Comparisons evaluate to 0 or 1
+            Some(plain(ast::TypeName::Int(ast::IntType::I64)))
+        } else {
+            Some(first_type)
+        }
+    }
+
+    fn visit_call(&mut self, call: &ast::Call) -> Option<ast::Type> {
+        let name = call.func_name.value.as_str();
+        if LINUX_BUILTINS.contains(&name) {
+            self.linux_only(&format!("Function {name}"), &call.span);
+        }
+        if matches!(name, "print" | "eprint" | "println" | "eprintln") {
+            self.visit_print(call);
+            return Some(plain(ast::TypeName::Unit));
+        }
+        if name == "read" {
+            // An optional file descriptor comes first
+            let params: &[Param] = match call.args.len() {
+                3 => &[Param::Int, Param::String, Param::Int],
+                _ => &[Param::String, Param::Int],
+            };
+            self.visit_builtin_args(call, params);
+            if let Some(buffer) = call.args.get(params.len() - 2) {
+                self.visit_read_buffer(buffer);
+            }
+            return Some(plain(ast::TypeName::Int(ast::IntType::I64)));
+        }
+        if let Some((params, return_type)) = builtin(name) {
+            // Only `_start` saves the initial stack these read from
+            if self.emit == Emit::Obj && matches!(name, "arg_count" | "arg" | "env") {
+                let error = Diagnostic::error(
+                    "E0206",
+                    format!("Function {name} is not available in object files"),
+                )
+                .with_primary(&call.span, "reads the initial stack of the process")
+                .with_help("pass the value from C to an exported function instead");
+                self.errors.push(error);
+            }
+            self.visit_builtin_args(call, params);
+            if let ("open", [_, mode]) = (name, call.args.as_slice()) {
+                self.visit_open_mode(mode);
+            }
+            return Some(plain(return_type));
+        }
+
+        let signature = self.functions[name].clone();
+        if signature.args.len() != call.args.len() {
+            self.error(
+                "E0202",
+                format!(
+                    "Function {name} expects {} arguments, found {}",
+                    signature.args.len(),
+                    call.args.len()
+                ),
+                &call.span,
+                format!("expected {} arguments", signature.args.len()),
+            );
+        }
+        for (arg, expr) in signature.args.iter().zip(&call.args) {
+            let expected = &arg._type;
+            let Some(found) = self.visit_expression(expr, Some(expected)) else {
+                continue;
+            };
+            // A reference parameter also takes a reference variable as is
+            let found = match expr {
+                ast::Expression::Ident(id) if !expected.modifiers.is_empty() => self
+                    .lookup(id)
+                    .filter(|_type| !_type.modifiers.is_empty())
+                    .unwrap_or(found),
+                _ => found,
+            };
+            if !self.assignable(expr, &found, expected) {
+                let error = Diagnostic::error(
+                    "E0201",
+                    format!(
+                        "Function {name} expects {expected} for argument {}, found {found}",
+                        arg.name.value
+                    ),
+                )
+                .with_primary(&expr.span(), format!("expected {expected}, found {found}"))
+                .with_secondary(&arg.name.span, "parameter declared here");
+                self.errors.push(error);
+            }
+        }
+        Some(signature.return_type)
+    }
+
+    /// Bytes are read into the string of a variable, at most its length
+    fn visit_read_buffer(&mut self, buffer: &ast::Expression) {
+        let id = match buffer {
+            ast::Expression::Ident(id) => Some(id),
+            ast::Expression::Unary(ast::UnaryOperation::Ref(inner), _) => match inner.as_ref() {
+                ast::Expression::Ident(id) => Some(id),
+                _ => None,
+            },
+            _ => None,
+        };
+        let writable = id.is_some_and(|id| {
+            let symbol = self.bindings.get(id);
+            match symbol.kind {
+                SymbolKind::Variable => true,
+                // The caller only lends its string through a reference
+                SymbolKind::Parameter => self.lookup(id).is_some_and(|t| !t.modifiers.is_empty()),
+                _ => false,
+            }
+        });
+        if !writable {
+            let error = Diagnostic::error("E0206", "Function read reads into a string variable")
+                .with_primary(&buffer.span(), "not a variable")
+                .with_help("declare the string with `let` and pass it instead");
+            self.errors.push(error);
+        }
+    }
+
+    /// The flags of `open` are picked at compile time from its mode
+    fn visit_open_mode(&mut self, mode: &ast::Expression) {
+        let is_const = match mode {
+            ast::Expression::Ident(id) => self.const_strings.contains(&self.bindings.id(id)),
+            ast::Expression::Literal(ast::Literal::String(_), _) => true,
+            _ => false,
+        };
+        if !is_const {
+            let error = Diagnostic::error("E0206", "The mode of open must be a const string")
+                .with_primary(&mode.span(), "not a const string")
+                .with_help("declare the mode with `const`, e.g. `const r = .r`");
+            self.errors.push(error);
+        }
+    }
+
+    fn visit_builtin_args(&mut self, call: &ast::Call, params: &[Param]) {
+        let name = &call.func_name.value;
+        if params.len() != call.args.len() {
+            self.error(
+                "E0202",
+                format!(
+                    "Function {name} expects {} arguments, found {}",
+                    params.len(),
+                    call.args.len()
+                ),
+                &call.span,
+                format!("expected {} arguments", params.len()),
+            );
+        }
+        for (i, expr) in call.args.iter().enumerate() {
+            let param = params.get(i);
+            let expected = match param {
+                Some(Param::Int) => Some(plain(ast::TypeName::Int(ast::IntType::I64))),
+                _ => None,
+            };
+            let Some(found) = self.visit_expression(expr, expected.as_ref()) else {
+                continue;
+            };
+            let valid = match param {
+                Some(Param::Int) => is_int(&found),
+                Some(Param::String) => found.name == ast::TypeName::String,
+                None => true,
+            };
+            if !valid {
+                let expected = match param {
+                    Some(Param::Int) => "an integer",
+                    _ => "a string",
+                };
+                self.error(
+                    "E0201",
+                    format!(
+                        "Function {name} expects {expected} for argument {}, found {found}",
+                        i + 1
+                    ),
+                    &expr.span(),
+                    format!("expected {expected}"),
+                );
+            }
+        }
+    }
+
+    fn visit_print(&mut self, call: &ast::Call) {
+        let name = &call.func_name.value;
+        match call.args.as_slice() {
+            [] if name.ends_with("ln") => (),
+            [format_expr @ ast::Expression::Literal(ast::Literal::String(format), _), args @ ..] => {
+                self.types.insert(format_expr, plain(ast::TypeName::String));
+                let pieces = format::parse(format);
+                let placeholders = pieces
+                    .iter()
+                    .filter_map(|piece| match piece {
+                        format::Piece::Arg(int_format) => Some(int_format),
+                        format::Piece::Text(_) => None,
+                    })
+                    .collect::<Vec<_>>();
+                if placeholders.len() != args.len() {
+                    self.error(
+                        "E0205",
+                        format!(
+                            "Format string {format:?} expects {} arguments, found {}",
+                            placeholders.len(),
+                            args.len()
+                        ),
+                        &format_expr.span(),
+                        format!("{} placeholders", placeholders.len()),
+                    );
+                }
+                for (i, arg) in args.iter().enumerate() {
+                    let found = self.visit_printable(name, arg);
+                    let laid_out = matches!(placeholders.get(i), Some(Some(_)));
+                    if laid_out {
+                        self.linux_only("Laying out integers", &arg.span());
+                    }
+                    if laid_out && found.is_some_and(|found| !is_int(&found)) {
+                        self.error(
+                            "E0205",
+                            format!("Only integers can be laid out in format string {format:?}"),
+                            &arg.span(),
+                            "expected an integer",
+                        );
+                    }
+                }
+            }
+            [arg] => {
+                self.visit_printable(name, arg);
+            }
+            _ => self.error(
+                "E0202",
+                format!("Function {name} expects a format string and its arguments"),
+                &call.span,
+                "",
+            ),
+        }
+    }
+
+    fn visit_printable(&mut self, name: &str, arg: &ast::Expression) -> Option<ast::Type> {
+        let found = self.visit_expression(arg, None)?;
+        let printable = match found.name {
+            ast::TypeName::String => true,
+            // Only strings are printed through a reference
+            ast::TypeName::Int(_) | ast::TypeName::Char => found.modifiers.is_empty(),
+            _ => false,
+        };
+        if !printable {
+            self.error(
+                "E0201",
+                format!("Function {name} expects a string, char or integer, found {found}"),
+                &arg.span(),
+                "cannot be printed",
+            );
+        }
+        Some(found)
+    }
+
+    fn declare(&mut self, name: &ast::Ident, _type: Option<ast::Type>) {
+        self.variables.insert(self.bindings.id(name), _type);
+    }
+
+    /// Type of the variable `name` is bound to, `None` when it is unknown
+    fn lookup(&self, name: &ast::Ident) -> Option<ast::Type> {
+        self.variables
+            .get(&self.bindings.id(name))
+            .cloned()
+            .flatten()
+    }
+}
@@ -1,22 +1,55 @@
 use crate::emitter::ast::Literal;
 use std::{
-    collections::{hash_map::Entry, BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap},
     mem,
 };
 
-use super::{ast, Integer};
+use super::{ast, const_eval, resolver::Bindings, type_checker::Types};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Value {
     String(String),
-    Int(i64),
+    Int(ast::IntType, i64),
+    Char(char),
 }
 
 impl From<ast::Literal> for Value {
     fn from(value: ast::Literal) -> Self {
         match value {
             Literal::String(s) => Value::String(s),
-            Literal::Integer(i) => Value::Int(i.value),
+            Literal::Integer(i) => Value::Int(ast::IntType::I64, i.value as i64),
+            Literal::Char(c) => Value::Char(c),
+        }
+    }
+}
+
+impl Value {
+    /// Initial value of a variable whose value is only known at runtime
+    pub fn zero(type_name: &ast::TypeName) -> Self {
+        match type_name {
+            ast::TypeName::String => Value::String("".to_string()),
+            ast::TypeName::Int(int_type) => Value::Int(*int_type, 0),
+            ast::TypeName::Char => Value::Char('\0'),
+            ast::TypeName::Float => todo!(),
+            ast::TypeName::Bool => todo!(),
+            ast::TypeName::Unit => todo!(),
+        }
+    }
+
+    pub fn type_name(&self) -> ast::TypeName {
+        match self {
+            Value::String(_) => ast::TypeName::String,
+            Value::Int(int_type, _) => ast::TypeName::Int(*int_type),
+            Value::Char(_) => ast::TypeName::Char,
+        }
+    }
+
+    /// Integer type the value is computed with, chars are their u32 code point
+    pub fn int_type(&self) -> Option<ast::IntType> {
+        match self {
+            Value::Int(int_type, _) => Some(*int_type),
+            Value::Char(_) => Some(ast::IntType::U32),
+            Value::String(_) => None,
         }
     }
 }
@@ -29,16 +62,15 @@ pub struct DataRef {
     pub data: Vec<u8>,
 }
 
+/// Offset below RBP of the function frame the variable lives in
 #[derive(Clone, Debug)]
 pub enum StackLocation {
-    Block(u64),
     Function(u64),
 }
 
 impl From<StackLocation> for u64 {
     fn from(value: StackLocation) -> Self {
         match value {
-            StackLocation::Block(loc) => loc,
             StackLocation::Function(loc) => loc,
         }
     }
@@ -78,6 +110,11 @@ pub struct Variable {
 }
 
 impl Variable {
+    /// Bytes the variable takes in its function frame
+    pub fn slot_size(&self) -> u64 {
+        (self.value_size as u64).next_multiple_of(mem::size_of::<u64>() as u64)
+    }
+
     pub fn new(
         name: &str,
         value_type: Value,
@@ -85,11 +122,15 @@ impl Variable {
         value_loc: ValueLocation,
     ) -> Variable {
         let value_size = match &value_type {
+            // Strings computed at runtime are pointers to heap memory
+            Value::String(_) if reference => mem::size_of::<u64>(),
+            // Length, bytes and a NUL so C functions can read it
             Value::String(s) => match value_loc {
-                ValueLocation::Stack(_) => s.len() + mem::size_of::<u64>(),
+                ValueLocation::Stack(_) => mem::size_of::<u64>() + s.len() + 1,
                 ValueLocation::DataSection(_) => s.len(),
             },
-            Value::Int(_) => mem::size_of::<i64>(),
+            Value::Int(int_type, _) => int_type.size(),
+            Value::Char(_) => mem::size_of::<u32>(),
         };
 
         Variable {
@@ -104,36 +145,131 @@ impl Variable {
     pub fn as_vec(&self) -> Vec<u8> {
         match &self.value_type {
             Value::String(s) => [s.as_bytes().to_vec(), vec![0]].concat(),
-            Value::Int(i) => i.to_le_bytes().to_vec(),
+            Value::Int(int_type, i) => i.to_le_bytes()[..int_type.size()].to_vec(),
+            Value::Char(c) => (*c as u32).to_le_bytes().to_vec(),
         }
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub args: Vec<ast::Arg>,
+    pub return_type: ast::Type,
+    /// Declared with `extern`, follows the C calling convention
+    pub external: bool,
+    /// Declared with `export`, other objects can call it
+    pub export: bool,
+    /// Size of the local variable slots below the saved arguments
+    pub locals_size: u64,
+}
+
+/// Initial stack pointer, it points at argc followed by the argv and envp
+/// pointers
+pub const STACK_BASE: &str = "#stack_base";
+/// Next free byte of the heap
+pub const HEAP_TOP: &str = "#heap_top";
+/// End of the memory mapped for the heap
+pub const HEAP_END: &str = "#heap_end";
+/// Buffer stdin is read into, allocated on the first read
+pub const STDIN_BUFFER: &str = "#stdin_buffer";
+/// Next unread byte and end of the bytes in the stdin buffer
+pub const STDIN_START: &str = "#stdin_start";
+pub const STDIN_END: &str = "#stdin_end";
+/// Buffer stdout is written into, a string allocated on the first print
+pub const STDOUT_BUFFER: &str = "#stdout_buffer";
+/// How stdout is buffered, set up by the entry point of executables
+pub const STDOUT_MODE: &str = "#stdout_mode";
+/// Negative error code of the last read_file, 0 when it succeeded
+pub const FILE_ERROR: &str = "#file_error";
+
+/// Hidden variable holding the position of a loop over a string
+pub fn loop_cursor(scope: &str, var: &str) -> String {
+    format!("{scope}::{var}#cursor")
+}
+
+/// Const holding a string literal passed around by address
+pub fn string_literal(span: &ast::Span) -> String {
+    format!("#string@{}:{}", span.line, span.column)
+}
+
 pub fn build_variables(
     block: &ast::Block,
-) -> (BTreeMap<String, Variable>, HashMap<String, Vec<String>>) {
-    let mut variables_collector = VariablesCollector::default();
+    bindings: &Bindings,
+    types: &Types,
+) -> (BTreeMap<String, Variable>, HashMap<String, Function>) {
+    let mut variables_collector = VariablesCollector {
+        bindings: bindings.clone(),
+        types: types.clone(),
+        ..Default::default()
+    };
     variables_collector.visit_ast(block);
-    (
-        variables_collector.variables,
-        variables_collector.scope_variable,
-    )
+    (variables_collector.variables, variables_collector.functions)
 }
 
 #[derive(Default, Debug)]
 pub struct VariablesCollector {
     pub variables: BTreeMap<String, Variable>,
-    pub scope_variable: HashMap<String, Vec<String>>,
+    pub functions: HashMap<String, Function>,
+    bindings: Bindings,
+    types: Types,
+    // Stack variables of the function being visited, in declaration order
+    locals: Vec<String>,
 }
 
 impl VariablesCollector {
     pub fn visit_ast(&mut self, block: &ast::Block) {
+        // Runtime state lives in the data section
+        for id in [
+            STACK_BASE,
+            HEAP_TOP,
+            HEAP_END,
+            STDIN_BUFFER,
+            STDIN_START,
+            STDIN_END,
+            STDOUT_BUFFER,
+            STDOUT_MODE,
+            FILE_ERROR,
+        ] {
+            let value = Value::Int(ast::IntType::U64, 0);
+            self.variables.insert(
+                id.to_string(),
+                Variable::new(id, value, false, ValueLocation::DataSection(0)),
+            );
+        }
+
+        // Signatures first, calls can precede the callee declaration
+        for stmt in &block.stmts {
+            let (name, function) = match stmt {
+                ast::Statement::FuncDeclaration(func_decl) => (
+                    &func_decl.name,
+                    Function {
+                        args: func_decl.args.clone(),
+                        return_type: func_decl.return_type.clone(),
+                        external: false,
+                        export: func_decl.export,
+                        locals_size: 0,
+                    },
+                ),
+                ast::Statement::ExternFuncDeclaration(extern_decl) => (
+                    &extern_decl.name,
+                    Function {
+                        args: extern_decl.args.clone(),
+                        return_type: extern_decl.return_type.clone(),
+                        external: true,
+                        export: false,
+                        locals_size: 0,
+                    },
+                ),
+                _ => continue,
+            };
+            self.functions.insert(name.value.clone(), function);
+        }
         self.visit_block(block);
     }
 
     pub fn visit_block(&mut self, block: &ast::Block) {
         block.stmts.iter().for_each(|stmt| match stmt {
-            ast::Statement::Expression(_) => (),
+            ast::Statement::Expression(expr) => self.visit_expression(expr),
             ast::Statement::Loop(l) => self.visit_loop(l),
             ast::Statement::VarDeclaration(var_declaration) => {
                 self.visit_var_declaration(var_declaration, &block.scope);
@@ -142,88 +278,220 @@ impl VariablesCollector {
             ast::Statement::FuncDeclaration(func_declaration) => {
                 self.visit_func_declaration(func_declaration)
             }
+            ast::Statement::ExternFuncDeclaration(_) => (),
             ast::Statement::Block(block) => self.visit_block(block),
-            ast::Statement::Assignment(_) => (),
-            ast::Statement::ControlFlow(_) => (),
+            ast::Statement::Error => (),
+            ast::Statement::Assignment(assign) => self.visit_assignment(assign),
+            ast::Statement::ControlFlow(ast::ControlFlow::If(_if)) => {
+                self.visit_expression(&_if.condition);
+                self.visit_block(&_if.then);
+                if let Some(otherwise) = &_if.otherwise {
+                    self.visit_block(otherwise);
+                }
+            }
+            ast::Statement::ControlFlow(ast::ControlFlow::Return(expr)) => {
+                if let Some(expr) = expr {
+                    self.visit_expression(expr);
+                }
+            }
         });
     }
 
+    /// Lays out the string literals that are not stored into a variable
+    fn visit_expression(&mut self, expr: &ast::Expression) {
+        match expr {
+            ast::Expression::Literal(Literal::String(s), span) => {
+                let id = string_literal(span);
+                let value = Value::String(s.clone());
+                self.variables.insert(
+                    id.clone(),
+                    Variable::new(&id, value, false, ValueLocation::DataSection(0)),
+                );
+            }
+            ast::Expression::Literal(..) | ast::Expression::Ident(_) => (),
+            // Printed literals are written out by the print itself
+            ast::Expression::Call(call)
+                if matches!(
+                    call.func_name.value.as_str(),
+                    "print" | "eprint" | "println" | "eprintln"
+                ) =>
+            {
+                call.args
+                    .iter()
+                    .filter(|arg| !matches!(arg, ast::Expression::Literal(..)))
+                    .for_each(|arg| self.visit_expression(arg));
+            }
+            ast::Expression::Call(call) => {
+                call.args.iter().for_each(|arg| self.visit_expression(arg));
+            }
+            ast::Expression::Unary(unary, _) => match unary {
+                ast::UnaryOperation::Ref(inner)
+                | ast::UnaryOperation::Minus(inner)
+                | ast::UnaryOperation::Not(inner) => self.visit_expression(inner),
+            },
+            ast::Expression::Binary(binary) => {
+                let (lhs, rhs) = binary.operands();
+                self.visit_expression(lhs);
+                self.visit_expression(rhs);
+            }
+            ast::Expression::Index(index) => {
+                self.visit_expression(&index.value);
+                self.visit_expression(&index.index);
+            }
+        }
+    }
+
     fn visit_var_declaration(&mut self, var_decl: &ast::VarDeclaration, scope: &str) {
         let expr = match &var_decl.rhs {
             ast::RhsExpression::Expression(expr) => expr,
             ast::RhsExpression::Block(block) => todo!(),
         };
 
-        match expr {
-            ast::Expression::Literal(lit) => {
-                let value_loc = match var_decl.declarion_type {
-                    ast::VarDeclarationType::Let => ValueLocation::Stack(StackLocation::Block(0)),
-                    ast::VarDeclarationType::Const => ValueLocation::DataSection(0),
+        if !matches!(expr, ast::Expression::Literal(..)) {
+            self.visit_expression(expr);
+        }
+        let declared_type = var_decl._type.as_ref().map(|t| &t.name);
+
+        let value = match expr {
+            ast::Expression::Literal(lit, _) => match (Value::from(lit.clone()), declared_type) {
+                (Value::Int(_, n), Some(ast::TypeName::Int(int_type))) => {
+                    if !int_type.fits(int_type.value(n)) {
+                        panic!(
+                            "literal {n} does not fit in {int_type}: {}",
+                            var_decl.name.value
+                        );
+                    }
+                    Value::Int(*int_type, n)
+                }
+                (value, None) => value,
+                (value, Some(type_name)) if value.type_name() == *type_name => value,
+                (value, Some(type_name)) => {
+                    panic!(
+                        "Cannot assign {} to {type_name}: {}",
+                        value.type_name(),
+                        var_decl.name.value
+                    )
+                }
+            },
+            ast::Expression::Unary(ast::UnaryOperation::Ref(_), _) => todo!(),
+            _ if var_decl.declarion_type == ast::VarDeclarationType::Const => {
+                let int_type = match declared_type.unwrap_or(&self.types.get(expr).name) {
+                    ast::TypeName::Int(int_type) => *int_type,
+                    type_name => {
+                        panic!(
+                            "const {} of type {type_name} must be a literal",
+                            var_decl.name.value
+                        )
+                    }
                 };
-                let id = format!("{}::{}", scope, &var_decl.name.value);
+                let n = const_eval::eval_int(&self.variables, &self.bindings, expr, int_type)
+                    .unwrap_or_else(|error| panic!("{}", error.message))
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "const {} must be known at compile time",
+                            var_decl.name.value
+                        )
+                    });
+                Value::Int(int_type, n)
+            }
+            // Computed at runtime, the slot starts zeroed
+            _ => {
+                let type_name = declared_type.unwrap_or(&self.types.get(expr).name);
+                Value::zero(type_name)
+            }
+        };
+
+        // Strings computed at runtime are only pointed to
+        let reference =
+            matches!(value, Value::String(_)) && !matches!(expr, ast::Expression::Literal(_, _));
+
+        let id = format!("{}::{}", scope, &var_decl.name.value);
+        match var_decl.declarion_type {
+            ast::VarDeclarationType::Let => self.add_local(&id, value, reference),
+            ast::VarDeclarationType::Const => {
                 self.variables.insert(
                     id.clone(),
-                    Variable::new(&id, lit.clone().into(), false, value_loc),
+                    Variable::new(&id, value, false, ValueLocation::DataSection(0)),
                 );
-                self.add_to_scope(&scope, vec![id]);
             }
-            _ => todo!(),
+        }
+    }
+
+    // A string slot must fit the longest string assigned to it
+    fn visit_assignment(&mut self, assign: &ast::Assignment) {
+        let ast::RhsExpression::Expression(expr) = &assign.rhs else {
+            return;
+        };
+        let ast::Expression::Literal(Literal::String(s), _) = expr else {
+            self.visit_expression(expr);
+            return;
+        };
+        let id = &self.bindings.get(&assign.variable_name).path;
+        let variable = self.variables.get_mut(id).unwrap();
+        if matches!(variable.value_loc, ValueLocation::Stack(_)) && !variable.reference {
+            let value_size = mem::size_of::<u64>() + s.len() + 1;
+            variable.value_size = variable.value_size.max(value_size);
         }
     }
 
     fn visit_func_declaration(&mut self, func_decl: &ast::FuncDeclaration) {
-        for arg in &func_decl.args {
-            let has_ref = !arg._type.modifiers.is_empty();
-            let lit = match arg._type.name {
-                ast::TypeName::String => Literal::String("".to_string()),
-                ast::TypeName::Int => Literal::Integer(Integer { value: 0 }),
-                ast::TypeName::Float => todo!(),
-                ast::TypeName::Bool => todo!(),
-                ast::TypeName::Unit => todo!(),
-            };
+        // Arguments are saved right below the saved RBP
+        for (i, arg) in func_decl.args.iter().enumerate() {
+            // Strings are passed by address
+            let has_ref =
+                !arg._type.modifiers.is_empty() || arg._type.name == ast::TypeName::String;
+            let value = Value::zero(&arg._type.name);
 
             let id = format!("{}::{}", func_decl.body.scope, &arg.name.value);
+            let value_loc = ValueLocation::Stack(StackLocation::Function(
+                (mem::size_of::<u64>() * (i + 1)) as u64,
+            ));
 
-            self.variables.insert(
-                id.clone(),
-                Variable::new(
-                    &id,
-                    lit.into(),
-                    has_ref,
-                    ValueLocation::Stack(StackLocation::Function(0)),
-                ),
-            );
-            self.add_to_scope(&func_decl.body.scope, vec![id.clone()]);
+            self.variables
+                .insert(id.clone(), Variable::new(&id, value, has_ref, value_loc));
         }
 
         self.visit_block(&func_decl.body);
+
+        // Locals follow the arguments, one slot per variable of any nested block
+        let args_size = (mem::size_of::<u64>() * func_decl.args.len()) as u64;
+        let mut offset = args_size;
+        for id in mem::take(&mut self.locals) {
+            let variable = self.variables.get_mut(&id).unwrap();
+            offset += variable.slot_size();
+            variable.value_loc = ValueLocation::Stack(StackLocation::Function(offset));
+        }
+        self.functions
+            .get_mut(&func_decl.name.value)
+            .unwrap()
+            .locals_size = offset - args_size;
     }
 
-    // fn visit_declaration(&mut self, statement: &ast::Statement) {}
     fn visit_loop(&mut self, l: &ast::Loop) {
-        let id = &l.var;
-        let lit = Literal::Integer(ast::Integer {
-            value: l.start as i64,
-        });
+        let id = format!("{}::{}", l.body.scope, &l.var.value);
 
-        let value_loc = ValueLocation::Stack(StackLocation::Block(0));
-
-        let id = format!("{}::{}", l.body.scope, &id.value);
-        self.variables
-            .insert(id.clone(), Variable::new(&id, lit.into(), false, value_loc));
+        let value = match &l.iter {
+            ast::LoopIter::Range { .. } => Value::Int(ast::IntType::I64, 0),
+            ast::LoopIter::Collection(collection) => {
+                self.visit_expression(collection);
+                // Byte offset of the next char
+                let cursor_id = loop_cursor(&l.body.scope, &l.var.value);
+                self.add_local(&cursor_id, Value::Int(ast::IntType::U64, 0), false);
+                Value::Char('\0')
+            }
+        };
+        self.add_local(&id, value, false);
 
         self.visit_block(&l.body);
-        self.add_to_scope(&l.body.scope, vec![id.clone()]);
     }
 
-    fn add_to_scope(&mut self, parent: &str, symbols: Vec<String>) {
-        match self.scope_variable.entry(parent.to_string()) {
-            Entry::Vacant(v) => {
-                v.insert(symbols);
-            }
-            Entry::Occupied(mut o) => {
-                o.get_mut().extend(symbols);
-            }
-        }
+    // The frame offset is assigned once the whole function is visited
+    fn add_local(&mut self, id: &str, value: Value, reference: bool) {
+        let value_loc = ValueLocation::Stack(StackLocation::Function(0));
+        self.variables.insert(
+            id.to_string(),
+            Variable::new(id, value, reference, value_loc),
+        );
+        self.locals.push(id.to_string());
     }
 }
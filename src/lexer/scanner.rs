@@ -1,136 +1,331 @@
-use super::token::Token;
+use std::sync::Arc;
 
-fn whitespace(s: &str) -> (Option<Token>, usize) {
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use super::token::{Span, SpannedToken, Token};
+use crate::diagnostics::Diagnostic;
+
+/// Token found at the start of the input and its length, `None` when the
+/// input does not start with this kind of token. Errors come with the length
+/// of the invalid lexeme, skipped to scan on.
+type Scanned = Result<(Option<Token>, usize), (String, usize)>;
+
+/// Error about the first char of `s`
+fn invalid(s: &str, message: String) -> Scanned {
+    let length = s.chars().next().map_or(0, char::len_utf8);
+    Err((message, length))
+}
+
+fn whitespace(s: &str) -> Scanned {
     let c = s.chars().next().unwrap();
-    if matches!(c, ' ' | '\t') {
-        (Some(Token::Whitespace), 1)
+    if matches!(c, ' ' | '\t' | '\r') {
+        Ok((Some(Token::Whitespace), 1))
     } else {
-        (None, 0)
+        Ok((None, 0))
     }
 }
 
-fn newline(s: &str) -> (Option<Token>, usize) {
+fn newline(s: &str) -> Scanned {
     let c = s.chars().next().unwrap();
     if c == '\n' {
-        (Some(Token::Newline), 1)
+        Ok((Some(Token::Newline), 1))
     } else {
-        (None, 0)
+        Ok((None, 0))
     }
 }
 
-fn operator(s: &str) -> (Option<Token>, usize) {
+// Comments are trivia, a block comment spanning lines counts as a newline
+fn comment(s: &str) -> Scanned {
+    if s.starts_with("//") {
+        let i = s.find('\n').unwrap_or(s.len());
+        return Ok((Some(Token::Whitespace), i));
+    }
+    if !s.starts_with("/*") {
+        return Ok((None, 0));
+    }
+    let Some(i) = s[2..].find("*/") else {
+        // Everything up to the end is the comment
+        return Err(("Unterminated block comment".to_owned(), s.len()));
+    };
+    let i = i + 4;
+    match s[..i].contains('\n') {
+        true => Ok((Some(Token::Newline), i)),
+        false => Ok((Some(Token::Whitespace), i)),
+    }
+}
+
+fn operator(s: &str) -> Scanned {
+    match s.get(..2) {
+        Some("==") => return Ok((Some(Token::EqualEqual), 2)),
+        Some("!=") => return Ok((Some(Token::NotEqual), 2)),
+        Some("<=") => return Ok((Some(Token::LessEqual), 2)),
+        Some(">=") => return Ok((Some(Token::GreaterEqual), 2)),
+        _ => (),
+    }
     let c = s.chars().next().unwrap();
     match c {
-        '=' => (Some(Token::Equal), 1),
-        '(' => (Some(Token::LeftP), 1),
-        ')' => (Some(Token::RightP), 1),
-        '{' => (Some(Token::BlockStart), 1),
-        '}' => (Some(Token::BlockEnd), 1),
-        ':' => (Some(Token::Column), 1),
-        '&' => (Some(Token::Ref), 1),
-        _ => (None, 0),
+        '=' => Ok((Some(Token::Equal), 1)),
+        '(' => Ok((Some(Token::LeftP), 1)),
+        ')' => Ok((Some(Token::RightP), 1)),
+        '[' => Ok((Some(Token::LeftBracket), 1)),
+        ']' => Ok((Some(Token::RightBracket), 1)),
+        '{' => Ok((Some(Token::BlockStart), 1)),
+        '}' => Ok((Some(Token::BlockEnd), 1)),
+        ':' => Ok((Some(Token::Column), 1)),
+        ',' => Ok((Some(Token::Comma), 1)),
+        '&' => Ok((Some(Token::Ref), 1)),
+        '+' => Ok((Some(Token::Plus), 1)),
+        '-' => Ok((Some(Token::Minus), 1)),
+        '*' => Ok((Some(Token::Mul), 1)),
+        '/' => Ok((Some(Token::Div), 1)),
+        '%' => Ok((Some(Token::Rem), 1)),
+        '<' => Ok((Some(Token::Less), 1)),
+        '>' => Ok((Some(Token::Greater), 1)),
+        _ => Ok((None, 0)),
     }
 }
 
 const ESCAPE_CHARACTERS_MAP: [(&str, &str); 2] = [("\n", "\\n"), ("\t", "\\t")];
 
-fn string(s: &str) -> (Option<Token>, usize) {
+fn string(s: &str) -> Scanned {
     if !s.starts_with('.') {
-        return (None, 0);
+        return Ok((None, 0));
     }
-    let i = s.len();
-    let mut s = s[1..].to_string();
+    // Runs to the end of the line
+    let line = s.split('\n').next().unwrap();
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let i = line.len();
+    let mut s = line[1..].to_string();
     for (c, escaped_c) in ESCAPE_CHARACTERS_MAP {
         s = s.replace(escaped_c, c);
     }
-    (Some(Token::String(s)), i)
+    Ok((Some(Token::String(s)), i))
 }
 
-fn number(s: &str) -> (Option<Token>, usize) {
-    let mut i: usize = 0;
-
-    for c in s.chars() {
-        if !c.is_numeric() {
-            break;
+fn quoted_string(s: &str) -> Scanned {
+    let Some(rest) = s.strip_prefix('"') else {
+        return Ok((None, 0));
+    };
+    let mut string = String::new();
+    // Reported once the whole literal is skipped
+    let mut error = None;
+    let mut chars = rest.chars();
+    loop {
+        match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('0') => string.push('\0'),
+                Some('\\') => string.push('\\'),
+                Some('"') => string.push('"'),
+                c => {
+                    error.get_or_insert(format!("Invalid escape character: {c:?}"));
+                }
+            },
+            Some(c) => string.push(c),
+            None => return Err(("Unterminated string literal".to_owned(), s.len())),
         }
-        i += 1;
     }
+    let length = s.len() - chars.as_str().len();
+    match error {
+        Some(message) => Err((message, length)),
+        None => Ok((Some(Token::String(string)), length)),
+    }
+}
+
+fn character(s: &str) -> Scanned {
+    let Some(rest) = s.strip_prefix('\'') else {
+        return Ok((None, 0));
+    };
+    let mut chars = rest.chars();
+    let c = match chars.next() {
+        Some('\\') => match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('\'') => '\'',
+            c => {
+                // Skip the closing quote too
+                if chars.as_str().starts_with('\'') {
+                    chars.next();
+                }
+                let message = format!("Invalid escape character: {c:?}");
+                return Err((message, s.len() - chars.as_str().len()));
+            }
+        },
+        Some(c) => c,
+        None => return invalid(s, "Unterminated char literal".to_owned()),
+    };
+    if chars.next() != Some('\'') {
+        return invalid(s, "Unterminated char literal".to_owned());
+    }
+    Ok((Some(Token::Char(c)), s.len() - chars.as_str().len()))
+}
+
+fn number(s: &str) -> Scanned {
+    let i = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
     if i == 0 {
-        return (None, 0);
+        return Ok((None, 0));
     }
     let token = {
         match s[..i].parse() {
             Ok(value) => Some(Token::Number(value)),
             Err(err) => match err.kind() {
                 std::num::IntErrorKind::Empty => None,
-                _ => panic!("{err}"),
+                _ => return Err((err.to_string(), i)),
             },
         }
     };
-    (token, i)
+    Ok((token, i))
 }
 
-fn range(s: &str) -> (Option<Token>, usize) {
-    if s.len() > 1 && &s[..2] == ".." {
-        (Some(Token::Range), 2)
+fn range(s: &str) -> Scanned {
+    if s.starts_with("..") {
+        Ok((Some(Token::Range), 2))
     } else {
-        (None, 0)
+        Ok((None, 0))
     }
 }
 
-fn identifier(s: &str) -> (Option<Token>, usize) {
-    if !s.chars().next().unwrap().is_alphabetic() {
-        return (None, 0);
-    }
-    let mut i: usize = 0;
-    for c in s.chars() {
-        if !c.is_alphanumeric() && !matches!(c, '_' | '!') {
-            break;
-        }
-        i += 1;
+// Follows the Unicode XID rules, like Rust identifiers
+fn identifier(s: &str) -> Scanned {
+    let first = s.chars().next().unwrap();
+    if !is_xid_start(first) && first != '_' {
+        return Ok((None, 0));
     }
-    (Some(Token::Ident(s[..i].to_string())), i)
+    // Lengths are in bytes, identifiers can hold any letter
+    let i = s
+        .find(|c: char| !is_xid_continue(c) && c != '!')
+        .unwrap_or(s.len());
+    Ok((Some(Token::Ident(s[..i].to_string())), i))
 }
 
-type Parser = fn(&str) -> (Option<Token>, usize);
+type Parser = fn(&str) -> Scanned;
 
-fn scan_token(s: &str) -> (Option<Token>, usize) {
+fn scan_token(s: &str) -> Scanned {
     let parsers: Vec<Parser> = vec![
-        newline, operator, range, string, number, identifier, whitespace,
+        newline,
+        comment,
+        operator,
+        range,
+        string,
+        quoted_string,
+        character,
+        number,
+        identifier,
+        whitespace,
     ];
     for (_i, parser) in parsers.iter().enumerate() {
-        let (token, advanced) = parser(s);
+        let (token, advanced) = parser(s)?;
         // dbg!(i, &token, advanced);
         if token.is_some() {
-            return (token, advanced);
+            return Ok((token, advanced));
         }
     }
 
-    panic!("Invalid lexeme")
+    let c = s.chars().next().unwrap();
+    invalid(s, format!("Invalid character {c:?}"))
+}
+
+/// Iterator over the tokens of a whole source file. Whitespace, comments
+/// and newlines are trivia: they are skipped, and the first newline before
+/// a token is recorded on it for the parser to end statements.
+pub struct Scanner<'a> {
+    file: Arc<str>,
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
-pub fn scan(source_code: String) -> Vec<Token> {
-    let mut tokens: Vec<Token> = vec![Token::BlockStart];
-    for line in source_code.lines() {
-        if line.trim().is_empty() {
-            continue;
+impl<'a> Scanner<'a> {
+    pub fn new(file: &str, source: &'a str) -> Self {
+        Self {
+            file: file.into(),
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn span(&self, length: usize) -> Span {
+        Span {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+            start: self.offset,
+            end: self.offset + length,
+        }
+    }
+
+    fn advance(&mut self, length: usize) {
+        for c in self.source[self.offset..self.offset + length].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
         }
-        let mut start = 0;
-        let mut line_tokens: Vec<Token> = vec![];
-        while start < line.len() {
-            let (token, advanced) = scan_token(&line[start..]);
-            if let Some(token) = token {
-                println!("Token: {:?}", token);
-                if token != Token::Whitespace {
-                    line_tokens.push(token);
+        self.offset += length;
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    /// A token, or an error for a lexeme that starts no token, which is
+    /// skipped
+    type Item = Result<SpannedToken, Diagnostic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut newline = None;
+        while self.offset < self.source.len() {
+            let rest = &self.source[self.offset..];
+            let (token, advanced) = match scan_token(rest) {
+                Ok((token, advanced)) => (token.unwrap(), advanced),
+                Err((message, skipped)) => {
+                    let length = rest.chars().next().map_or(0, char::len_utf8);
+                    let error =
+                        Diagnostic::error("E0002", message).with_primary(&self.span(length), "");
+                    self.advance(skipped);
+                    return Some(Err(error));
+                }
+            };
+            let span = self.span(advanced);
+            self.advance(advanced);
+            match token {
+                Token::Whitespace => (),
+                Token::Newline => {
+                    newline.get_or_insert(span);
+                }
+                token => {
+                    return Some(Ok(SpannedToken {
+                        token,
+                        span,
+                        newline,
+                    }))
                 }
             }
-            start += advanced;
         }
-        tokens.extend(line_tokens.clone());
-        tokens.push(Token::StatementEnd);
-        println!("Token: {:?}", Token::StatementEnd);
+        None
+    }
+}
+
+/// Splits `source_code` read from `file` into tokens, each with its
+/// location, or reports the lexemes that start no token
+pub fn scan(file: &str, source_code: &str) -> Result<Vec<SpannedToken>, Vec<Diagnostic>> {
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    for token in Scanner::new(file, source_code) {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(tokens),
+        false => Err(errors),
     }
-    tokens.push(Token::BlockEnd);
-    tokens
 }
@@ -1,17 +1,108 @@
+use std::{fmt::Display, sync::Arc};
+
+/// Lexeme of the language. `Whitespace` and `Newline` are trivia that the
+/// scanner skips, `StatementEnd` is inserted by the parser.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Token {
     Equal,
     Newline,
     LeftP,
     RightP,
+    LeftBracket,
+    RightBracket,
     BlockStart,
     BlockEnd,
     Whitespace,
     Range,
     StatementEnd,
     Column,
+    Comma,
     Ref,
+    Plus,
+    Minus,
+    Mul,
+    Div,
+    Rem,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    EqualEqual,
+    NotEqual,
     Ident(String),
     String(String),
-    Number(i64),
+    Char(char),
+    Number(u64),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Equal => f.write_str("="),
+            Token::Newline | Token::StatementEnd => f.write_str("end of line"),
+            Token::LeftP => f.write_str("("),
+            Token::RightP => f.write_str(")"),
+            Token::LeftBracket => f.write_str("["),
+            Token::RightBracket => f.write_str("]"),
+            Token::BlockStart => f.write_str("{"),
+            Token::BlockEnd => f.write_str("}"),
+            Token::Whitespace => f.write_str("whitespace"),
+            Token::Range => f.write_str(".."),
+            Token::Column => f.write_str(":"),
+            Token::Comma => f.write_str(","),
+            Token::Ref => f.write_str("&"),
+            Token::Plus => f.write_str("+"),
+            Token::Minus => f.write_str("-"),
+            Token::Mul => f.write_str("*"),
+            Token::Div => f.write_str("/"),
+            Token::Rem => f.write_str("%"),
+            Token::Less => f.write_str("<"),
+            Token::LessEqual => f.write_str("<="),
+            Token::Greater => f.write_str(">"),
+            Token::GreaterEqual => f.write_str(">="),
+            Token::EqualEqual => f.write_str("=="),
+            Token::NotEqual => f.write_str("!="),
+            Token::Ident(id) => f.write_str(id),
+            Token::String(s) => write!(f, "{s:?}"),
+            Token::Char(c) => write!(f, "{c:?}"),
+            Token::Number(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+/// Location of a token or AST node in the source code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub file: Arc<str>,
+    /// 1-based line of the first character
+    pub line: usize,
+    /// 1-based column of the first character, counted in chars
+    pub column: usize,
+    /// Byte offsets of the first character and past the last one
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Span from the start of `self` to the end of `other`
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..self.clone()
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+    /// First newline between the previous token and this one
+    pub newline: Option<Span>,
 }
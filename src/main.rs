@@ -1,21 +1,156 @@
-use std::{env, fs, path::Path};
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    process,
+    sync::{Arc, Mutex},
+};
 
+use diagnostics::{Diagnostic, Format};
+use emitter::{lints::Lint, Emit};
 use parser::ast_printer;
 
+mod diagnostics;
 mod emitter;
 mod lexer;
 mod parser;
 
 fn main() {
-    let source_filename = env::args().nth(1).expect("Missing source filename");
-    let source_code = fs::read_to_string(source_filename).unwrap();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Read first so that errors about the other arguments use it too
+    let mut format = Format::Human;
+    while let Some(i) = args
+        .iter()
+        .position(|arg| arg.starts_with("--error-format"))
+    {
+        let arg = args.remove(i);
+        let name = match arg.strip_prefix("--error-format") {
+            Some("") if i < args.len() => args.remove(i),
+            Some(name) if name.starts_with('=') => name[1..].to_owned(),
+            _ => fail(format, "missing --error-format kind"),
+        };
+        format = Format::from_name(&name).unwrap_or_else(|| {
+            fail(
+                format,
+                format!("unknown --error-format kind {name}, expected human or json"),
+            )
+        });
+    }
+    let emit = match args.iter().position(|arg| arg == "--emit") {
+        Some(i) => {
+            let kind = args
+                .get(i + 1)
+                .cloned()
+                .unwrap_or_else(|| fail(format, "missing --emit kind"));
+            args.drain(i..(i + 2).min(args.len()));
+            match kind.as_str() {
+                "exe" => Emit::Exe,
+                "obj" => Emit::Obj,
+                _ => fail(
+                    format,
+                    format!("unknown --emit kind {kind}, expected exe or obj"),
+                ),
+            }
+        }
+        None => Emit::Exe,
+    };
+    let checked = match args.iter().position(|arg| arg == "--checked") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    // Object files are only written in the ELF format, and the Windows
+    // runtime cannot panic
+    if cfg!(target_os = "windows") {
+        if emit == Emit::Obj {
+            fail(format, "--emit obj is not supported on Windows");
+        }
+        if checked {
+            fail(format, "--checked is not supported on Windows");
+        }
+    }
+    let mut allowed = vec![];
+    while let Some(i) = args.iter().position(|arg| arg == "--allow") {
+        let name = args
+            .get(i + 1)
+            .cloned()
+            .unwrap_or_else(|| fail(format, "missing --allow lint"));
+        args.drain(i..=i + 1);
+        let lint = Lint::from_name(&name).unwrap_or_else(|| {
+            let names: Vec<_> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+            fail(
+                format,
+                format!("unknown lint {name}, expected one of {}", names.join(", ")),
+            )
+        });
+        allowed.push(lint);
+    }
+    let deny_warnings = match args.iter().position(|arg| arg == "--deny-warnings") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    let source_filename = args
+        .first()
+        .unwrap_or_else(|| fail(format, "missing source filename"));
+    let source = fs::read_to_string(source_filename)
+        .unwrap_or_else(|err| fail(format, format!("cannot read {source_filename}: {err}")));
     let output_filename =
-        Path::new(&env::args().nth(2).unwrap_or("./hello.exe".to_owned())).to_path_buf();
+        Path::new(&args.get(1).cloned().unwrap_or("./hello.exe".to_owned())).to_path_buf();
+
+    let report = |errors: Vec<Diagnostic>| -> ! {
+        diagnostics::emit(&errors, &source, format);
+        process::exit(1);
+    };
 
-    let tokens = lexer::scanner::scan(source_code);
-    let ast = parser::parse(tokens).unwrap();
+    let tokens =
+        lexer::scanner::scan(source_filename, &source).unwrap_or_else(|errors| report(errors));
+    let ast = parser::parse(tokens).unwrap_or_else(|errors| report(errors));
     let output = ast_printer::visit_block(&ast);
     println!("{output}");
 
-    emitter::build_executable(&ast, output_filename);
+    let bindings = emitter::resolver::resolve(&ast).unwrap_or_else(|errors| report(errors));
+    let types =
+        emitter::type_checker::check(&ast, &bindings, emit).unwrap_or_else(|errors| report(errors));
+
+    let warnings = emitter::lints::check(&ast, &bindings, &allowed);
+    diagnostics::emit(&warnings, &source, format);
+    if deny_warnings && !warnings.is_empty() {
+        fail(format, "warnings are denied by --deny-warnings");
+    }
+
+    // The checks above report every error in the program, a panic in code
+    // generation is a bug of the compiler and is shown as such
+    let message = Arc::new(Mutex::new(None));
+    let hook_message = message.clone();
+    panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let text = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "compiler panicked".to_owned());
+        *hook_message.lock().unwrap() = Some(text);
+    }));
+    let built = panic::catch_unwind(AssertUnwindSafe(|| {
+        emitter::build_executable(&ast, &bindings, &types, output_filename, emit, checked)
+    }));
+    if built.is_err() {
+        let text = message.lock().unwrap().take();
+        let text = text.unwrap_or_else(|| "compiler panicked".to_owned());
+        let error = Diagnostic::error("E0301", format!("internal compiler error: {text}"))
+            .with_help("this is a bug in the compiler, please report it");
+        report(vec![error]);
+    }
+}
+
+/// Reports a failure that is not about a location in the program and exits
+fn fail(format: Format, message: impl Into<String>) -> ! {
+    diagnostics::emit(&[Diagnostic::failure(message)], "", format);
+    process::exit(1);
 }
@@ -1,72 +1,292 @@
 pub mod ast_printer;
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Context, Result};
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use crate::{emitter::ast, lexer::token::Token};
+use crate::{
+    diagnostics::Diagnostic,
+    emitter::ast,
+    lexer::token::{Span, SpannedToken, Token},
+};
 
 /*
 program := item*
 item := func | declaration
-func_declaration := "func" ident (args) type block
+func_declaration := ["export"] func_signature block
+extern_func_declaration := "extern" func_signature
+func_signature := "func" ident (args) [type]
 args := (arg,)*
-arg := "const"? ident: type
+arg := "const"? ident type
 type := &* type_name
-type_name := ("String" | "int" | "float" | "bool" | unit)
+type_name := ("String" | "int" | int_type | "float" | "bool" | unit)
+int_type := ("i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64")
 block := { statement*  }
 statement := var_declaration | assignment | expression
-                        | func_declaration | loop | control_flow | block
-loop := "for" ident..ident block
+                        | func_declaration | extern_func_declaration
+                        | loop | control_flow | block
+loop := "for" ident "in" (number..number | expression) block
 
 var_declaration := ("let" | "const") ident [: type] "=" rhs_expression
 assignment := ident "=" rhs_expression
 
 rhs_expression := block | expression
-expression := unary | binary | literal | ident | call
-unary := unary_operator expression
-binary := expression binary_operator expression
-unary_operator := - | !
-operator :=  + | - | * | /
+expression := comparison
+comparison := additive (comparison_operator additive)*
+additive := term (("+" | "-") term)*
+term := primary (("*" | "/" | "%") primary)*
+primary := (call | unary | literal | ident | "(" expression ")") ("[" expression "]")*
+unary := unary_operator primary
+unary_operator := - | &
+comparison_operator := < | <= | > | >= | == | !=
 
 call := ident(expression(, expression)+)
-literal := string | int | float | bool
+literal := string | int | char | float | bool
+char := ' character '
 bool := "true" | "false"
 string := . ident
 
-control_flow := return_cf
-return_cf := "return" expression
+control_flow := return_cf | if_cf
+return_cf := "return" [expression]
+if_cf := "if" expression block ["else" (if_cf | block)]
 */
 
-pub fn parse(tokens: Vec<Token>) -> Result<ast::Block> {
-    let (block, tokens) = block(&tokens, "global")?;
-    assert!(tokens.is_empty(), "there are unparsed tokens: {tokens:#?}");
-    Ok(block)
+thread_local! {
+    // Location of each scanned token
+    static SPANS: RefCell<Vec<Span>> = const { RefCell::new(vec![]) };
+    // Syntax errors the parser recovered from
+    static ERRORS: RefCell<Vec<ParseError>> = const { RefCell::new(vec![]) };
 }
 
-fn block<'a>(tokens: &'a [Token], scope: &str) -> Result<(ast::Block, &'a [Token])> {
-    let tokens = match_next(tokens, Token::BlockStart)?;
+/// Syntax error at a token, the root cause of every error of the parser
+#[derive(Debug, Clone)]
+struct ParseError {
+    span: Span,
+    message: String,
+}
+
+impl ParseError {
+    fn at(tokens: &[Token], message: String) -> Self {
+        // Running out of tokens is an error at the last one
+        let span = SPANS.with_borrow(|spans| {
+            let i = (spans.len() - tokens.len()).min(spans.len() - 1);
+            spans[i].clone()
+        });
+        Self { span, message }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::error("E0001", &error.message).with_primary(&error.span, "")
+    }
+}
 
-    let mut tokens = skip(&tokens, Token::StatementEnd);
+/// Parses the whole program, or reports every syntax error found in it
+pub fn parse(tokens: Vec<SpannedToken>) -> Result<ast::Block, Vec<Diagnostic>> {
+    if tokens.is_empty() {
+        return Ok(ast::Block::new("global".to_owned(), vec![], vec![]));
+    }
+    let (tokens, spans) = end_statements(tokens);
+    SPANS.set(spans);
+    ERRORS.take();
+
+    let result = program(&tokens);
+    let mut diagnostics: Vec<Diagnostic> = ERRORS.take().iter().map(Diagnostic::from).collect();
+    match result {
+        Ok(block) if diagnostics.is_empty() => return Ok(block),
+        Ok(_) => (),
+        // Errors that could not be recovered from end the parsing
+        Err(error) => match error.root_cause().downcast_ref::<ParseError>() {
+            Some(error) => diagnostics.push(error.into()),
+            None => diagnostics.push(Diagnostic::error("E0001", format!("{error:#}"))),
+        },
+    }
+    Err(diagnostics)
+}
 
+// Inserts a statement end at each newline following a token that can end a
+// statement, the way Go inserts semicolons. Expressions and argument lists
+// can span lines by breaking them after an operator, `(` or `,`.
+fn end_statements(tokens: Vec<SpannedToken>) -> (Vec<Token>, Vec<Span>) {
     let mut result = vec![];
-    let mut stmt: Option<ast::Statement>;
+    let mut ends_statement = false;
+    for SpannedToken {
+        token,
+        span,
+        newline,
+    } in tokens
+    {
+        if let Some(newline) = newline.filter(|_| ends_statement) {
+            result.push((Token::StatementEnd, newline));
+        }
+        ends_statement = matches!(
+            token,
+            Token::Ident(_)
+                | Token::Number(_)
+                | Token::String(_)
+                | Token::Char(_)
+                | Token::RightP
+                | Token::RightBracket
+                | Token::BlockEnd
+        );
+        result.push((token, span));
+    }
+
+    // The file is a block, its braces are empty spans at both ends
+    if let (Some((_, first)), Some((_, last))) = (result.first(), result.last()) {
+        let start = Span {
+            end: first.start,
+            ..first.clone()
+        };
+        let end = Span {
+            start: last.end,
+            ..last.clone()
+        };
+        result.insert(0, (Token::BlockStart, start));
+        result.push((Token::BlockEnd, end));
+    }
+    result.into_iter().unzip()
+}
+
+// A `}` closing the block of the file early is reported and the statements
+// after it are still parsed
+fn program(tokens: &[Token]) -> Result<ast::Block> {
+    let mut tokens = match_next(tokens, Token::BlockStart)?;
+    let mut stmts = vec![];
+    let mut spans = vec![];
     loop {
-        (stmt, tokens) = statement(&tokens, scope)?;
-        if tokens.is_empty() {
-            bail!("Expected end of block");
+        let (block_stmts, block_spans, rest) = statements(tokens, "global")?;
+        stmts.extend(block_stmts);
+        spans.extend(block_spans);
+        if rest.is_empty() {
+            break;
         }
+        ERRORS.with_borrow_mut(|errors| {
+            errors.push(ParseError {
+                span: span_before(rest),
+                message: "Unmatched }".to_owned(),
+            })
+        });
+        tokens = rest;
+    }
+    Ok(ast::Block::new("global".to_owned(), stmts, spans))
+}
 
-        tokens = match_next(tokens, Token::StatementEnd).context("statement end not reached")?;
+// Every slice the parser works on is a suffix of the scanned tokens
+fn span(tokens: &[Token]) -> Span {
+    SPANS.with_borrow(|spans| spans[spans.len() - tokens.len()].clone())
+}
 
-        result.push(stmt.unwrap());
+// Span of the last token before `rest`
+fn span_before(rest: &[Token]) -> Span {
+    SPANS.with_borrow(|spans| spans[spans.len() - rest.len() - 1].clone())
+}
+
+// Span from the first token of `start` to the last one before `rest`
+fn span_since(start: &[Token], rest: &[Token]) -> Span {
+    span(start).to(&span_before(rest))
+}
 
+// Describes the next token for error messages
+fn found(tokens: &[Token]) -> String {
+    match tokens.first() {
+        Some(token) => token.to_string(),
+        None => "end of file".to_owned(),
+    }
+}
+
+fn block<'a>(tokens: &'a [Token], scope: &str) -> Result<(ast::Block, &'a [Token])> {
+    let tokens = match_next(tokens, Token::BlockStart)?;
+    let (stmts, spans, tokens) = statements(tokens, scope)?;
+    Ok((ast::Block::new(scope.to_string(), stmts, spans), tokens))
+}
+
+// Parses the statements of a block up to and including its `}`. A statement
+// that fails to parse is reported, replaced by an error node and skipped, so
+// that the following ones are still parsed
+fn statements<'a>(
+    mut tokens: &'a [Token],
+    scope: &str,
+) -> Result<(Vec<ast::Statement>, Vec<Span>, &'a [Token])> {
+    let mut result = vec![];
+    let mut spans = vec![];
+    loop {
+        // Empty statements
+        while let Ok(_tokens) = match_next(tokens, Token::StatementEnd) {
+            tokens = _tokens;
+        }
         if let Ok(_tokens) = match_next(tokens, Token::BlockEnd) {
             tokens = _tokens;
             break;
         };
+        if tokens.is_empty() {
+            bail!(ParseError::at(
+                tokens,
+                "Expected }, found end of file".to_owned()
+            ));
+        }
+
+        let start = tokens;
+        let parsed = statement(tokens, scope).and_then(|(stmt, rest)| {
+            // The last statement of a block can share its line with the `}`
+            let end = match rest.first() {
+                Some(Token::BlockEnd) => rest,
+                _ => match_next(rest, Token::StatementEnd)?,
+            };
+            Ok((stmt.unwrap(), rest, end))
+        });
+        match parsed {
+            Ok((stmt, rest, end)) => {
+                spans.push(span_since(start, rest));
+                result.push(stmt);
+                tokens = end;
+            }
+            Err(error) => {
+                let rest = synchronize(start);
+                if rest.is_empty() {
+                    return Err(error);
+                }
+                let error = match error.root_cause().downcast_ref::<ParseError>() {
+                    Some(error) => error.clone(),
+                    None => ParseError::at(start, format!("{error:#}")),
+                };
+                ERRORS.with_borrow_mut(|errors| errors.push(error));
+                spans.push(span_since(start, rest));
+                result.push(ast::Statement::Error);
+                tokens = rest;
+            }
+        }
     }
-    Ok((ast::Block::new(scope.to_string(), result), tokens))
+    Ok((result, spans, tokens))
+}
+
+// Skips a statement that failed to parse, up to the end of its line or of
+// the enclosing block. Blocks opened by the statement are skipped whole.
+fn synchronize(tokens: &[Token]) -> &[Token] {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::BlockStart => depth += 1,
+            Token::BlockEnd if depth == 0 => return &tokens[i..],
+            Token::BlockEnd => depth -= 1,
+            Token::StatementEnd if depth == 0 => return &tokens[i + 1..],
+            _ => (),
+        }
+    }
+    &tokens[tokens.len()..]
 }
 
 fn statement<'a>(
@@ -81,6 +301,13 @@ fn statement<'a>(
         func_definition(tokens, scope).context("Couldn't parse statement")?
     {
         Ok((Some(ast::Statement::FuncDeclaration(func_def)), tokens))
+    } else if let (Some(extern_decl), tokens) =
+        extern_func_declaration(tokens).context("Couldn't parse extern function declaration")?
+    {
+        Ok((
+            Some(ast::Statement::ExternFuncDeclaration(extern_decl)),
+            tokens,
+        ))
     } else if let (Some(assgn), tokens) =
         assignment(tokens, scope).context("Couldn't parse assignment statement")?
     {
@@ -96,7 +323,10 @@ fn statement<'a>(
     {
         Ok((Some(ast::Statement::Expression(expr)), tokens))
     } else {
-        bail!("Unexpected statement: {:?}", &tokens[0])
+        bail!(ParseError::at(
+            tokens,
+            format!("Expected statement, found {}", found(tokens))
+        ))
     }
 }
 
@@ -111,11 +341,21 @@ fn var_declaration<'a>(
         return Ok((None, tokens));
     };
 
-    let (id, tokens) =
-        match_ident(tokens).context(format!("Expected identifier, found: {:#?}", &tokens[0]))?;
+    let (id, tokens) = match_name(tokens).map_err(|_| {
+        ParseError::at(
+            tokens,
+            format!("Expected identifier, found {}", found(tokens)),
+        )
+    })?;
 
-    let tokens = match_next(tokens, Token::Equal)
-        .context(format!("Expected =, found: {:#?}", &tokens[0]))?;
+    let (var_type, tokens) = if let Ok(tokens) = match_next(tokens, Token::Column) {
+        let (var_type, tokens) = _type(tokens)?;
+        (Some(var_type), tokens)
+    } else {
+        (None, tokens)
+    };
+
+    let tokens = match_next(tokens, Token::Equal)?;
 
     let (Some(expr), tokens) = expression(&tokens, scope).context("Expected expression")? else {
         return Ok((None, tokens));
@@ -123,7 +363,8 @@ fn var_declaration<'a>(
 
     Ok((
         Some(ast::VarDeclaration::new(
-            ident(id),
+            id,
+            var_type,
             ast::RhsExpression::Expression(expr),
             decl_type,
         )),
@@ -135,18 +376,59 @@ fn func_definition<'a>(
     tokens: &'a [Token],
     scope: &str,
 ) -> Result<(Option<ast::FuncDeclaration>, &'a [Token])> {
-    let Ok((keyword, tokens)) = match_ident(tokens) else {
-        return Ok((None, tokens));
+    let (export, tokens) = match starts_with_ident(tokens, "export") {
+        Ok(tokens) => (true, tokens),
+        Err(_) => (false, tokens),
     };
-    if keyword != "func" {
+    let tokens = match starts_with_ident(tokens, "func") {
+        Ok(tokens) => tokens,
+        Err(_) if export => bail!(ParseError::at(
+            tokens,
+            format!("Expected func after export, found {}", found(tokens))
+        )),
+        Err(_) => return Ok((None, tokens)),
+    };
+
+    let (func_name, args, return_type, tokens) = func_signature(tokens)?;
+
+    let current_scope = format!("{}::{}", scope, func_name.value);
+    let (block, tokens) = block(&tokens, &current_scope)?;
+
+    let func_definition = ast::FuncDeclaration::new(func_name, args, return_type, block, export);
+
+    Ok((Some(func_definition), tokens))
+}
+
+fn extern_func_declaration<'a>(
+    tokens: &'a [Token],
+) -> Result<(Option<ast::ExternFuncDeclaration>, &'a [Token])> {
+    let Ok(tokens) = starts_with_ident(tokens, "extern") else {
         return Ok((None, tokens));
-    }
+    };
+    let tokens = starts_with_ident(tokens, "func")?;
 
-    let (func_name, tokens) =
-        match_ident(tokens).context(format!("Expected function name found: {:#?}", &tokens[0]))?;
+    let (func_name, args, return_type, tokens) = func_signature(tokens)?;
 
-    let tokens = match_next(tokens, Token::LeftP)
-        .context(format!("Expected (, found: {:#?}", &tokens[0]))?;
+    Ok((
+        Some(ast::ExternFuncDeclaration::new(
+            func_name,
+            args,
+            return_type,
+        )),
+        tokens,
+    ))
+}
+
+// Parses the function name, arguments and return type after "func"
+fn func_signature(tokens: &[Token]) -> Result<(ast::Ident, Vec<ast::Arg>, ast::Type, &[Token])> {
+    let (func_name, tokens) = match_name(tokens).map_err(|_| {
+        ParseError::at(
+            tokens,
+            format!("Expected function name, found {}", found(tokens)),
+        )
+    })?;
+
+    let tokens = match_next(tokens, Token::LeftP)?;
 
     let mut tokens = tokens;
     let mut args: Vec<ast::Arg> = vec![];
@@ -158,49 +440,64 @@ fn func_definition<'a>(
         };
         let _tokens = tokens;
 
-        let (arg_name, mut _tokens) = match_ident(_tokens)
-            .context(format!("Expected argument name, found: {:#?}", &tokens[0]))?;
+        let (arg_name, _tokens) = match_name(_tokens).map_err(|_| {
+            ParseError::at(
+                tokens,
+                format!("Expected argument name, found {}", found(tokens)),
+            )
+        })?;
 
-        let has_ref = if let Ok(__tokens) = match_next(_tokens, Token::Ref) {
-            _tokens = __tokens;
-            true
-        } else {
-            false
-        };
-
-        let (arg_type, _tokens) = match_ident(_tokens)
-            .context(format!("Expected argument type, found: {:#?}", &tokens[0]))?;
+        let (arg_type, _tokens) = _type(_tokens).map_err(|_| {
+            ParseError::at(
+                _tokens,
+                format!("Expected argument type, found {}", found(_tokens)),
+            )
+        })?;
 
-        let type_modifiers = if has_ref {
-            vec![ast::TypeModifer::Ref]
-        } else {
-            vec![]
-        };
-        let arg_type = ast::Type::new(arg_type.into(), type_modifiers);
-
-        let arg_name = ident(arg_name);
         let arg = ast::Arg::new(arg_name, arg_type);
         args.push(arg);
 
-        tokens = _tokens;
+        tokens = skip(_tokens, Token::Comma);
     }
 
-    let func_name = ident(func_name);
+    let (return_type, tokens) = if matches!(tokens.first(), Some(Token::Ident(_) | Token::Ref)) {
+        _type(tokens).map_err(|_| {
+            ParseError::at(
+                tokens,
+                format!("Expected return type, found {}", found(tokens)),
+            )
+        })?
+    } else {
+        (ast::Type::new(ast::TypeName::Unit, vec![]), tokens)
+    };
 
-    let current_scope = format!("{}::{}", scope, func_name.value);
-    let (block, tokens) = block(&tokens, &current_scope)?;
+    Ok((func_name, args, return_type, tokens))
+}
 
-    let return_type = ast::Type::new(ast::TypeName::Unit, vec![]);
-    let func_definition = ast::FuncDeclaration::new(func_name.clone(), args, return_type, block);
+fn _type<'a>(tokens: &'a [Token]) -> Result<(ast::Type, &'a [Token])> {
+    let (has_ref, tokens) = if let Ok(tokens) = match_next(tokens, Token::Ref) {
+        (true, tokens)
+    } else {
+        (false, tokens)
+    };
 
-    Ok((Some(func_definition), tokens))
+    let (name, rest) = match_ident(tokens)?;
+    let type_name =
+        ast::TypeName::try_from(name).map_err(|message| ParseError::at(tokens, message))?;
+
+    let type_modifiers = if has_ref {
+        vec![ast::TypeModifer::Ref]
+    } else {
+        vec![]
+    };
+    Ok((ast::Type::new(type_name, type_modifiers), rest))
 }
 
 fn assignment<'a>(
     tokens: &'a [Token],
     scope: &str,
 ) -> Result<(Option<ast::Assignment>, &'a [Token])> {
-    let Ok((id, tokens)) = match_ident(tokens) else {
+    let Ok((id, tokens)) = match_name(tokens) else {
         return Ok((None, tokens));
     };
 
@@ -210,7 +507,7 @@ fn assignment<'a>(
 
     let (rhs_expr, tokens) = rhs_expression(&tokens, scope)?;
     if let Some(rhs_expr) = rhs_expr {
-        Ok((Some(ast::Assignment::new(ident(id), rhs_expr)), tokens))
+        Ok((Some(ast::Assignment::new(id, rhs_expr)), tokens))
     } else {
         Ok((None, tokens))
     }
@@ -226,23 +523,108 @@ fn rhs_expression<'a>(
     if let (block, tokens) = block(tokens, scope)? {
         return todo!(); //Ok((Some(ast::RhsExpression::Block(Block::new(id, stmts))), tokens));
     }
-    bail!("invalid rhs expression: {:?}", &tokens)
+    bail!(ParseError::at(
+        tokens,
+        format!("Expected expression, found {}", found(tokens))
+    ))
 }
 
+type BinaryConstructor = fn(Box<ast::Expression>, Box<ast::Expression>) -> ast::BinaryOperation;
+
+// From the lowest to the highest precedence
+const BINARY_OPERATORS: &[&[(Token, BinaryConstructor)]] = &[
+    &[
+        (Token::Less, ast::BinaryOperation::Less),
+        (Token::LessEqual, ast::BinaryOperation::LessEqual),
+        (Token::Greater, ast::BinaryOperation::Greater),
+        (Token::GreaterEqual, ast::BinaryOperation::GreaterEqual),
+        (Token::EqualEqual, ast::BinaryOperation::Equal),
+        (Token::NotEqual, ast::BinaryOperation::NotEqual),
+    ],
+    &[
+        (Token::Plus, ast::BinaryOperation::Plus),
+        (Token::Minus, ast::BinaryOperation::Minus),
+    ],
+    &[
+        (Token::Mul, ast::BinaryOperation::Mul),
+        (Token::Div, ast::BinaryOperation::Div),
+        (Token::Rem, ast::BinaryOperation::Rem),
+    ],
+];
+
 fn expression<'a>(
     tokens: &'a [Token],
     scope: &str,
 ) -> Result<(Option<ast::Expression>, &'a [Token])> {
+    binary(tokens, scope, 0)
+}
+
+fn binary<'a>(
+    tokens: &'a [Token],
+    scope: &str,
+    precedence: usize,
+) -> Result<(Option<ast::Expression>, &'a [Token])> {
+    if precedence == BINARY_OPERATORS.len() {
+        return primary(tokens, scope);
+    }
+
+    let (Some(mut lhs), mut tokens) = binary(tokens, scope, precedence + 1)? else {
+        return Ok((None, tokens));
+    };
+
+    while let Some((_, operation)) = BINARY_OPERATORS[precedence]
+        .iter()
+        .find(|(operator, _)| tokens.first() == Some(operator))
+    {
+        let (rhs, _tokens) = binary(advance(tokens), scope, precedence + 1)?;
+        let rhs = rhs.ok_or_else(|| {
+            let message = format!("Expected expression after {}", found(tokens));
+            ParseError::at(advance(tokens), message)
+        })?;
+        lhs = ast::Expression::Binary(operation(Box::new(lhs), Box::new(rhs)));
+        tokens = _tokens;
+    }
+
+    Ok((Some(lhs), tokens))
+}
+
+fn primary<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Expression>, &'a [Token])> {
+    let start = tokens;
+    let (Some(mut expr), mut tokens) = operand(tokens, scope)? else {
+        return Ok((None, tokens));
+    };
+    while let Ok(rest) = match_next(tokens, Token::LeftBracket) {
+        let (Some(index), rest) = expression(rest, scope)? else {
+            bail!(ParseError::at(
+                rest,
+                format!("Expected index expression, found {}", found(rest))
+            ));
+        };
+        tokens = match_next(rest, Token::RightBracket)?;
+        expr = ast::Expression::Index(ast::Index::new(expr, index, span_since(start, tokens)));
+    }
+    Ok((Some(expr), tokens))
+}
+
+fn operand<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Expression>, &'a [Token])> {
     if let (Some(call), tokens) = call(tokens, scope)? {
         return Ok((Some(ast::Expression::Call(call)), tokens));
-    } else if let (Some(unary), tokens) = unary(tokens, scope)? {
-        return Ok((Some(ast::Expression::Unary(unary)), tokens));
-    } else if let (Some(literal), tokens) = literal(tokens, scope) {
-        return Ok((Some(ast::Expression::Literal(literal)), tokens));
-    } else if let [Token::Ident(id), ..] = tokens {
-        return Ok((Some(ast::Expression::Ident(ident(id))), &tokens[1..]));
+    } else if let (Some(unary), rest) = unary(tokens, scope)? {
+        let span = span_since(tokens, rest);
+        return Ok((Some(ast::Expression::Unary(unary, span)), rest));
+    } else if let (Some(literal), rest) = literal(tokens, scope) {
+        return Ok((Some(ast::Expression::Literal(literal, span(tokens))), rest));
+    } else if let Ok((id, rest)) = match_name(tokens) {
+        return Ok((Some(ast::Expression::Ident(id)), rest));
+    } else if let Ok(tokens) = match_next(tokens, Token::LeftP) {
+        let (expr, rest) = expression(tokens, scope)?;
+        let tokens = match_next(rest, Token::RightP)?;
+        return Ok((expr, tokens));
     }
-    bail!("invalid expression: {:?}", &tokens)
+    bail!(ParseError::at(
+        tokens,
+        format!("Expected expression, found {}", found(tokens))
+    ))
 }
 
 fn control_flow<'a>(
@@ -253,13 +635,54 @@ fn control_flow<'a>(
         return Ok((None, tokens));
     };
 
-    if keyword == "return" {
-        Ok((Some(ast::ControlFlow::Return(None)), &tokens))
-    } else {
-        Ok((None, tokens))
+    match keyword {
+        "return" => {
+            if matches!(tokens.first(), Some(Token::StatementEnd | Token::BlockEnd)) {
+                return Ok((Some(ast::ControlFlow::Return(None)), tokens));
+            }
+            let (expr, tokens) = expression(tokens, scope).context("Expected return value")?;
+            Ok((Some(ast::ControlFlow::Return(expr)), tokens))
+        }
+        "if" => {
+            let (_if, tokens) = _if(tokens, scope)?;
+            Ok((Some(ast::ControlFlow::If(_if)), tokens))
+        }
+        _ => Ok((None, tokens)),
     }
 }
 
+static IF_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+// Parses the rest of an if statement after the "if" keyword
+fn _if<'a>(tokens: &'a [Token], scope: &str) -> Result<(ast::If, &'a [Token])> {
+    let (Some(condition), tokens) = expression(tokens, scope)? else {
+        bail!(ParseError::at(
+            tokens,
+            format!("Expected if condition, found {}", found(tokens))
+        ));
+    };
+
+    let n = IF_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let (then, tokens) = block(tokens, &format!("{scope}::if_{n}"))?;
+
+    let Ok(tokens) = starts_with_ident(tokens, "else") else {
+        return Ok((ast::If::new(condition, then, None), tokens));
+    };
+
+    let else_scope = format!("{scope}::else_{n}");
+    let (otherwise, tokens) = if let Ok(if_tokens) = starts_with_ident(tokens, "if") {
+        // else if is an else block holding a single if
+        let (_if, rest) = _if(if_tokens, &else_scope)?;
+        let spans = vec![span_since(tokens, rest)];
+        let stmts = vec![ast::Statement::ControlFlow(ast::ControlFlow::If(_if))];
+        (ast::Block::new(else_scope, stmts, spans), rest)
+    } else {
+        block(tokens, &else_scope)?
+    };
+
+    Ok((ast::If::new(condition, then, Some(otherwise)), tokens))
+}
+
 static LOOP_COUNTER: AtomicUsize = AtomicUsize::new(1);
 
 fn _loop<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Loop>, &'a [Token])> {
@@ -267,11 +690,30 @@ fn _loop<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Loop>, &'a
         return Ok((None, tokens));
     };
 
-    let (var, tokens) = match_ident(tokens)?;
+    let (var, tokens) = match_name(tokens).map_err(|_| {
+        ParseError::at(
+            tokens,
+            format!("Expected loop variable, found {}", found(tokens)),
+        )
+    })?;
     let tokens = starts_with_ident(tokens, "in")?;
-    let (start, tokens) = match_number(tokens)?;
-    let tokens = match_next(tokens, Token::Range)?;
-    let (end, tokens) = match_number(tokens)?;
+    let (Some(expr), tokens) = expression(tokens, scope)? else {
+        bail!(ParseError::at(
+            tokens,
+            format!("Expected loop range, found {}", found(tokens))
+        ));
+    };
+    let (iter, tokens) = if let Ok(tokens) = match_next(tokens, Token::Range) {
+        let (Some(end), tokens) = expression(tokens, scope)? else {
+            bail!(ParseError::at(
+                tokens,
+                format!("Expected end of loop range, found {}", found(tokens))
+            ));
+        };
+        (ast::LoopIter::Range { start: expr, end }, tokens)
+    } else {
+        (ast::LoopIter::Collection(expr), tokens)
+    };
 
     let current_scope = format!(
         "{scope}::loop_{}",
@@ -281,11 +723,8 @@ fn _loop<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Loop>, &'a
     let (block, tokens) = block(tokens, &current_scope)?;
     Ok((
         Some(ast::Loop {
-            var: ast::Ident {
-                value: var.to_string(),
-            },
-            start: *start as u64,
-            end: *end as u64,
+            var,
+            iter,
             body: block,
         }),
         tokens,
@@ -298,12 +737,20 @@ fn unary<'a>(
 ) -> Result<(Option<ast::UnaryOperation>, &'a [Token])> {
     if let Ok(tokens) = match_next(tokens, Token::Ref) {
         let (Some(expr), tokens): (Option<ast::Expression>, &[Token]) =
-            expression(tokens, scope).context("Expected expression")?
+            primary(tokens, scope).context("Expected expression")?
         else {
             return Ok((None, tokens));
         };
         return Ok((Some(ast::UnaryOperation::Ref(Box::new(expr))), tokens));
     }
+    if let Ok(tokens) = match_next(tokens, Token::Minus) {
+        let (Some(expr), tokens): (Option<ast::Expression>, &[Token]) =
+            primary(tokens, scope).context("Expected expression")?
+        else {
+            return Ok((None, tokens));
+        };
+        return Ok((Some(ast::UnaryOperation::Minus(Box::new(expr))), tokens));
+    }
     Ok((None, tokens))
 }
 
@@ -311,6 +758,7 @@ fn literal<'a>(tokens: &'a [Token], scope: &str) -> (Option<ast::Literal>, &'a [
     match tokens {
         [Token::String(s), ..] => (Some(ast::Literal::String(string(s))), &tokens[1..]),
         [Token::Number(num), ..] => (Some(ast::Literal::Integer(number(num))), &tokens[1..]),
+        [Token::Char(c), ..] => (Some(ast::Literal::Char(*c)), &tokens[1..]),
         _ => (None, tokens),
     }
 }
@@ -319,20 +767,15 @@ fn string(str: &str) -> String {
     str.to_owned()
 }
 
-fn ident(ident: &str) -> ast::Ident {
-    ast::Ident {
-        value: ident.to_owned(),
-    }
-}
-
-fn number(number: &i64) -> ast::Integer {
+fn number(number: &u64) -> ast::Integer {
     ast::Integer {
         value: number.to_owned(),
     }
 }
 
 fn call<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Call>, &'a [Token])> {
-    let Ok((id, tokens)) = match_ident(tokens) else {
+    let start = tokens;
+    let Ok((id, tokens)) = match_name(tokens) else {
         return Ok((None, tokens));
     };
 
@@ -341,17 +784,40 @@ fn call<'a>(tokens: &'a [Token], scope: &str) -> Result<(Option<ast::Call>, &'a
     };
 
     if &tokens[0] == &Token::RightP {
-        return Ok((Some(ast::Call::new(ident(id), vec![])), &tokens[1..]));
+        let tokens = &tokens[1..];
+        let call = ast::Call::new(id, vec![], span_since(start, tokens));
+        return Ok((Some(call), tokens));
     }
 
-    let (expr, tokens) = expression(tokens, scope).unwrap();
+    let mut tokens = tokens;
+    let mut args = vec![];
+    loop {
+        let (Some(expr), _tokens) = expression(tokens, scope)? else {
+            bail!(ParseError::at(
+                tokens,
+                format!("Expected call argument, found {}", found(tokens))
+            ));
+        };
+        args.push(expr);
+
+        match match_next(_tokens, Token::Comma) {
+            // A trailing comma lets the closing parenthesis go on its own line
+            Ok(_tokens) if _tokens.first() == Some(&Token::RightP) => {
+                tokens = _tokens;
+                break;
+            }
+            Ok(_tokens) => tokens = _tokens,
+            Err(_) => {
+                tokens = _tokens;
+                break;
+            }
+        }
+    }
 
     let tokens = match_next(tokens, Token::RightP)?;
 
-    Ok((
-        expr.map(|expr| ast::Call::new(ident(id), vec![expr])),
-        &tokens,
-    ))
+    let call = ast::Call::new(id, args, span_since(start, tokens));
+    Ok((Some(call), tokens))
 }
 
 fn skip(tokens: &[Token], to_skip: Token) -> &[Token] {
@@ -367,14 +833,13 @@ fn skip(tokens: &[Token], to_skip: Token) -> &[Token] {
 }
 
 fn match_next(tokens: &[Token], target: Token) -> Result<&[Token]> {
-    if tokens
-        .first()
-        .ok_or(anyhow!("Expected token {:#?}", target))?
-        == &target
-    {
+    if tokens.first() == Some(&target) {
         return Ok(advance(&tokens));
     }
-    bail!("Unexptected token: {:#?}", target);
+    bail!(ParseError::at(
+        tokens,
+        format!("Expected {target}, found {}", found(tokens))
+    ));
 }
 
 fn advance<'a>(tokens: &[Token]) -> &[Token] {
@@ -382,31 +847,39 @@ fn advance<'a>(tokens: &[Token]) -> &[Token] {
 }
 
 fn match_ident<'a>(tokens: &[Token]) -> Result<(&str, &[Token])> {
-    let token = tokens.first().ok_or(anyhow!("Expected ident not found"))?;
-
-    match token {
-        Token::Ident(v) => {
+    match tokens.first() {
+        Some(Token::Ident(v)) => {
             return Ok((v, advance(&tokens)));
         }
-        _ => bail!("Expected ident but found: {:#?}", token),
+        _ => bail!(ParseError::at(
+            tokens,
+            format!("Expected identifier, found {}", found(tokens))
+        )),
     }
 }
 
-fn match_number<'a>(tokens: &[Token]) -> Result<(&i64, &[Token])> {
-    let token = tokens.first().ok_or(anyhow!("Expected number not found"))?;
-
-    match token {
-        Token::Number(v) => {
-            return Ok((v, advance(&tokens)));
-        }
-        _ => bail!("Expected number but found: {:#?}", token),
-    }
+// Matches an identifier naming a variable, function or argument
+fn match_name(tokens: &[Token]) -> Result<(ast::Ident, &[Token])> {
+    let (name, rest) = match_ident(tokens)?;
+    let ident = ast::Ident {
+        value: name.to_owned(),
+        span: span(tokens),
+    };
+    Ok((ident, rest))
 }
 
 fn starts_with_ident<'a>(tokens: &'a [Token], s: &str) -> Result<&'a [Token]> {
-    let (keyword, tokens) = match_ident(tokens)?;
+    let Ok((keyword, rest)) = match_ident(tokens) else {
+        bail!(ParseError::at(
+            tokens,
+            format!("Expected {s}, found {}", found(tokens))
+        ));
+    };
     if keyword != s {
-        bail!("Expected ident {s} but found: {:#?}", &tokens[0]);
+        bail!(ParseError::at(
+            tokens,
+            format!("Expected {s}, found {}", found(tokens))
+        ));
     }
-    Ok(&tokens)
+    Ok(&rest)
 }
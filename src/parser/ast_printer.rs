@@ -14,12 +14,20 @@ fn visit_statement(statement: &Statement) -> String {
         Statement::Expression(expr) => visit_expression(expr),
         Statement::VarDeclaration(VarDeclaration {
             name: ident,
+            _type,
             rhs: expr,
             declarion_type: assign_type,
+            ..
         }) => {
             let s1 = visit_ident(ident);
             let s2 = visit_rhs_expression(expr);
-            format!("{assign_type} {s1} = {s2}")
+            match _type {
+                Some(_type) => {
+                    let s_type = visit_type(_type);
+                    format!("{assign_type} {s1}:{s_type} = {s2}")
+                }
+                None => format!("{assign_type} {s1} = {s2}"),
+            }
         }
         Statement::Assignment(Assignment {
             variable_name: ident,
@@ -34,6 +42,7 @@ fn visit_statement(statement: &Statement) -> String {
             args,
             return_type,
             body: stmts,
+            export,
         }) => {
             let s_name = visit_ident(name);
             let s_args = args.iter().fold(String::new(), |mut acc, a| {
@@ -51,14 +60,42 @@ fn visit_statement(statement: &Statement) -> String {
             }
             let s_stmts = visit_block(stmts).replace("\n", "\n\t");
 
-            format!("func {result}{{\n\t{s_stmts} \n}}")
+            let export = if *export { "export " } else { "" };
+            format!("{export}func {result}{{\n\t{s_stmts} \n}}")
+        }
+        Statement::ExternFuncDeclaration(ExternFuncDeclaration {
+            name,
+            args,
+            return_type,
+        }) => {
+            let s_args = args
+                .iter()
+                .map(|a| format!("{} {}", visit_ident(&a.name), a._type))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut result = format!("extern func {} ({s_args})", visit_ident(name));
+            if return_type.name != TypeName::Unit {
+                result = format!("{result} {}", visit_type(return_type));
+            }
+            result
         }
         Statement::Block(stmts) => {
             format!("{{\n{}\n}}", visit_block(stmts))
         }
+        Statement::Error => "<error>".to_owned(),
         Statement::ControlFlow(cf) => format!("{cf:#?}"),
         Statement::Loop(l) => {
-            let s = format!("for {} in {}..{}", l.var.value, l.start, l.end);
+            let s = match &l.iter {
+                LoopIter::Range { start, end } => format!(
+                    "for {} in {}..{}",
+                    l.var.value,
+                    visit_expression(start),
+                    visit_expression(end)
+                ),
+                LoopIter::Collection(expr) => {
+                    format!("for {} in {}", l.var.value, visit_expression(expr))
+                }
+            };
             let body = visit_block(&l.body);
 
             s + " {\n" + &body + "\n}\n"
@@ -76,10 +113,11 @@ fn visit_rhs_expression(rhs_expression: &RhsExpression) -> String {
 fn visit_expression(expression: &Expression) -> String {
     match expression {
         Expression::Ident(ident) => visit_ident(ident),
-        Expression::Literal(literal) => visit_literal(literal),
+        Expression::Literal(literal, _) => visit_literal(literal),
         Expression::Call(Call {
             func_name,
             ref args,
+            ..
         }) => {
             let s1 = visit_ident(func_name);
             let s2 = args
@@ -89,12 +127,22 @@ fn visit_expression(expression: &Expression) -> String {
                 .join(", ");
             s1 + "(" + &s2 + ")"
         }
-        Expression::Unary(unary_operation) => match unary_operation {
-            UnaryOperation::Minus(expr) => todo!(),
+        Expression::Unary(unary_operation, _) => match unary_operation {
+            UnaryOperation::Minus(expr) => format!("-{}", visit_expression(expr)),
             UnaryOperation::Not(expr) => todo!(),
             UnaryOperation::Ref(expr) => format!("&{}", visit_expression(expr)),
         },
-        Expression::Binary(binary_operation) => todo!(),
+        Expression::Index(Index { value, index, .. }) => {
+            format!("{}[{}]", visit_expression(value), visit_expression(index))
+        }
+        Expression::Binary(binary_operation) => {
+            let (lhs, rhs) = binary_operation.operands();
+            format!(
+                "({} {binary_operation} {})",
+                visit_expression(lhs),
+                visit_expression(rhs)
+            )
+        }
     }
 }
 
@@ -106,6 +154,7 @@ fn visit_literal(literal: &Literal) -> String {
     match literal {
         Literal::String(str) => format!(".{str}").replace("\n", "\\n"),
         Literal::Integer(number) => visit_number(number),
+        Literal::Char(c) => format!("{c:?}"),
     }
 }
 
@@ -1,5 +1,8 @@
 use std::env;
-use std::process::Command;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
 
 #[test]
 fn test_hello() {
@@ -62,17 +65,55 @@ f2: let 3\n"
     )
 }
 
+#[test]
+fn test_int_types() {
+    let src = "int_types";
+    let output = compile_src(&src);
+    assert_eq!(
+        &output,
+        "-56
+44
+0
+-3
+-1
+1333333333
+1
+0
+-18
+9223372036854775799
+9223372036854775808
+-30000"
+    )
+}
+
 #[cfg(target_os = "linux")]
 fn compile_src(src: &str) -> String {
+    String::from_utf8(run_src(src).stdout).unwrap()
+}
+
+#[cfg(target_os = "linux")]
+fn run_src(src: &str) -> Output {
+    Command::new(build_src(src)).output().unwrap()
+}
+
+#[cfg(target_os = "linux")]
+fn build_src(src: &str) -> PathBuf {
+    build_src_with(src, src, &[])
+}
+
+#[cfg(target_os = "linux")]
+fn build_src_with(src: &str, name: &str, flags: &[&str]) -> PathBuf {
     let dest = env::current_dir()
         .unwrap()
-        .join(&format!("local/bin/{src}"));
+        .join(&format!("local/bin/{name}"));
     let src = env::current_dir()
         .unwrap()
         .join(&format!("tests/fixtures/{src}.jc"));
 
     let child = Command::new("cargo")
-        .args(&["run", src.to_str().unwrap(), dest.to_str().unwrap()])
+        .arg("run")
+        .args(flags)
+        .args(&[src.to_str().unwrap(), dest.to_str().unwrap()])
         .env("RUST_BACKTRACE", "1")
         .output()
         .unwrap();
@@ -81,16 +122,79 @@ fn compile_src(src: &str) -> String {
         panic!("{}", String::from_utf8(child.stderr).unwrap());
     }
 
-    let stdout = Command::new(dest.to_str().unwrap())
+    dest
+}
+
+/// Compiles a fixture that must be rejected and returns the compiler's stderr.
+#[cfg(target_os = "linux")]
+fn compile_error(src: &str) -> String {
+    compile_error_with(src, &[])
+}
+
+#[cfg(target_os = "linux")]
+fn compile_error_with(src: &str, flags: &[&str]) -> String {
+    let child = run_compiler(src, flags);
+    assert!(!child.status.success());
+    String::from_utf8(child.stderr).unwrap()
+}
+
+/// Compiles a fixture and returns the warnings reported on stderr
+#[cfg(target_os = "linux")]
+fn compile_warnings(src: &str, flags: &[&str]) -> Vec<String> {
+    let child = run_compiler(src, flags);
+    let stderr = String::from_utf8(child.stderr).unwrap();
+    assert!(child.status.success(), "{stderr}");
+    diagnostics(&stderr, "warning")
+}
+
+/// What a fixture prints when it panics at `location`, its `line:column`
+#[cfg(target_os = "linux")]
+fn panic_message(src: &str, location: &str, message: &str) -> String {
+    let src = env::current_dir()
+        .unwrap()
+        .join(format!("tests/fixtures/{src}.jc"));
+    format!("panic at {}:{location}: {message}\n", src.display())
+}
+
+/// Headers of the diagnostics of `severity` in `stderr`, each prefixed with
+/// the `line:column` of the location below it
+fn diagnostics(stderr: &str, severity: &str) -> Vec<String> {
+    let lines: Vec<&str> = stderr.lines().collect();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with(&format!("{severity}[")))
+        .map(|(i, header)| {
+            let location = lines
+                .get(i + 1)
+                .and_then(|line| line.trim_start().strip_prefix("--> "))
+                .map_or(String::new(), |path| {
+                    let mut parts: Vec<_> = path.rsplitn(3, ':').take(2).collect();
+                    parts.reverse();
+                    parts.join(":")
+                });
+            format!("{location} {header}")
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn run_compiler(src: &str, flags: &[&str]) -> Output {
+    let dest = env::current_dir().unwrap().join(format!("local/bin/{src}"));
+    let src = env::current_dir()
+        .unwrap()
+        .join(format!("tests/fixtures/{src}.jc"));
+
+    Command::new("cargo")
+        .arg("run")
+        .args(flags)
+        .args([src.to_str().unwrap(), dest.to_str().unwrap()])
         .output()
         .unwrap()
-        .stdout;
-    String::from_utf8(stdout).unwrap()
 }
 
 #[cfg(target_os = "windows")]
 fn compile_src(src: &str) -> String {
-    use std::process::Stdio;
     let dest = env::current_dir()
         .unwrap()
         .join(&format!("local/bin/{src}.exe"));
@@ -115,3 +219,547 @@ fn compile_src(src: &str) -> String {
         .stdout;
     String::from_utf8(stdout).unwrap().replace("\r\n", "\n")
 }
+
+#[test]
+fn test_chars() {
+    let src = "chars";
+    let output = compile_src(&src);
+    assert_eq!(
+        &output,
+        "h|é|l|l|o|,| |w|ö|r|l|d| |€|\n104\n195\n18\naé€\n1\n1\n"
+    );
+}
+
+#[test]
+fn test_strings() {
+    let output = compile_src("strings");
+    assert_eq!(
+        &output,
+        "[literal arg] 11\n[inline] 6\n[local] 5\n[local] 5\nliteral passed through\n4 1\na|b|c|\n"
+    );
+}
+
+#[test]
+fn test_recursion() {
+    let src = "recursion";
+    let output = compile_src(&src);
+    assert_eq!(&output, "3628800\n610\n110\n9\ndepth 0\ndepth 1\ndepth 2\n");
+}
+
+#[test]
+fn test_extern() {
+    let src = "extern";
+    let output = compile_src(&src);
+    assert_eq!(&output, "12\nhello from C\n42\n1235\n3\nllo\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_export() {
+    let dir = env::current_dir().unwrap();
+    let object = dir.join("local/bin/export.o");
+    let dest = dir.join("local/bin/export");
+
+    let child = Command::new("cargo")
+        .args(&[
+            "run",
+            "tests/fixtures/export.jc",
+            object.to_str().unwrap(),
+            "--emit",
+            "obj",
+        ])
+        .output()
+        .unwrap();
+    if !child.status.success() {
+        panic!("{}", String::from_utf8(child.stderr).unwrap());
+    }
+
+    let child = Command::new("gcc")
+        .args(&[
+            "-o",
+            dest.to_str().unwrap(),
+            "tests/fixtures/export_main.c",
+            object.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    if !child.status.success() {
+        panic!("{}", String::from_utf8(child.stderr).unwrap());
+    }
+
+    let stdout = Command::new(dest).output().unwrap().stdout;
+    assert_eq!(
+        String::from_utf8(stdout).unwrap(),
+        "hello from jc\n42 3628800 25\n"
+    );
+
+    let stderr = compile_error_with("export_args", &["--", "--emit", "obj"]);
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [
+            "2:8 error[E0206]: Function arg_count is not available in object files",
+            "3:20 error[E0206]: Function arg is not available in object files",
+        ]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_exit_code() {
+    let output = run_src("exit_code");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "exiting\n");
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = run_src("exit_builtin");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "012");
+    assert_eq!(output.status.code(), Some(30));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_args() {
+    let output = Command::new(build_src("args"))
+        .args(["héllo", "second", ""])
+        .env("JC_GREETING", "hi there")
+        .env_remove("JC_UNSET")
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "4\nhéllo\n6\nh|é|l|l|o|\nsecond\n\n0\nhi there\n0\nhi there[hi there|hi there]\nword\n"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_stdin() {
+    let mut child = Command::new(build_src("stdin"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The long line spans several reads of the stdin buffer
+    let input = format!(
+        "  12\n -30 tail\nabcdefgh\nhello wörld\n{}\nlast",
+        "x".repeat(5000)
+    );
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "-18\n6\n10\nabcdefgh\nh\n12: ello wörld\n5001: 4: last\n3\n"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_files() {
+    let binary = build_src("files");
+    let path = env::current_dir().unwrap().join("local/bin/files.txt");
+    let _ = std::fs::remove_file(&path);
+
+    let output = Command::new(&binary).arg(&path).output().unwrap();
+    let binary_size = std::fs::metadata(&binary).unwrap().len();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "11\n12\n0\nfirst line\nsecond line\n0\n5\nfirst\n-2\n0\n-2\n-9\n{binary_size}\n0\n"
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "first line\nsecond line\n"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_panic() {
+    let output = run_src("panic");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\n99\n12");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "to stderr\n42\n".to_owned() + &panic_message("panic", "2:5", "assertion failed")
+    );
+    assert_eq!(output.status.code(), Some(101));
+
+    let binary = build_src("panic_message");
+    let output = Command::new(&binary).arg("disk full").output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        panic_message("panic_message", "7:5", "disk full")
+    );
+    assert_eq!(output.status.code(), Some(101));
+    let output = Command::new(&binary).output().unwrap();
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        panic_message("panic_message", "5:9", "no reason given")
+    );
+
+    let output = run_src("panic_index");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        panic_message("panic_index", "5:11", "index out of bounds")
+    );
+    assert_eq!(output.status.code(), Some(101));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_checked() {
+    let output = run_src("checked");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "7\n-56\n1\n");
+    assert_eq!(output.status.code(), Some(0));
+
+    let binary = build_src_with("checked", "checked_on", &["--", "--checked"]);
+    let cases: [(&[&str], &str, &str, &str); 8] = [
+        (&[], "7\n", "8:15", "arithmetic overflow"),
+        (&["a"], "4\n", "13:15", "arithmetic overflow"),
+        (&["a", "b"], "3\n", "16:15", "division by zero"),
+        (&["a", "b", "c"], "4\n", "20:17", "arithmetic overflow"),
+        (&["a", "b", "c", "d"], "3\n", "25:15", "arithmetic overflow"),
+        (
+            &["a", "b", "c", "d", "e"],
+            "2\n",
+            "30:15",
+            "arithmetic overflow",
+        ),
+        (
+            &["a", "b", "c", "d", "e", "f"],
+            "1\n",
+            "34:15",
+            "arithmetic overflow",
+        ),
+        // Computed as i8 before it is widened
+        (
+            &["a", "b", "c", "d", "e", "f", "g"],
+            "7\n",
+            "37:25",
+            "arithmetic overflow",
+        ),
+    ];
+    for (args, stdout, location, message) in cases {
+        let output = Command::new(&binary).args(args).output().unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), stdout);
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            panic_message("checked", location, message)
+        );
+        assert_eq!(output.status.code(), Some(101));
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_flush() {
+    let binary = build_src("flush");
+    // stdout and stderr share a file so their order shows the buffering
+    let path = env::current_dir().unwrap().join("local/bin/flush.out");
+    let file = File::create(&path).unwrap();
+    let status = Command::new(binary)
+        .stdout(file.try_clone().unwrap())
+        .stderr(file)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let digits: String = (0..5000).map(|i| (i % 10).to_string()).collect();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!("21345\n{digits}\n3\nabc")
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_format() {
+    let output = Command::new(build_src("format"))
+        .args(["a", "b"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Number: 0
+Number: 1
+Number: 2
+jc says -42 and ü!
+[  -42] [-42  ] [-0042] [123456]
+[beef] [0000beef] [    ff] [ffffffd6]
+{} -84 {48880}
+
+no newline 3
+"
+    );
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "to stderr\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_consts() {
+    assert_eq!(
+        compile_src("consts"),
+        "1024 4096 2047 255 1\n123\n16777225\n1\n"
+    );
+    assert_eq!(
+        diagnostics(&compile_error("const_overflow"), "error"),
+        ["4:24 error[E0207]: overflow evaluating 100 * 2 as i8"]
+    );
+    // Reported before the lints evaluate the range
+    assert_eq!(
+        diagnostics(&compile_error("const_division"), "error"),
+        ["2:17 error[E0207]: division by zero evaluating 5 / 0"]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_type_errors() {
+    let stderr = compile_error("type_errors");
+    let errors = diagnostics(&stderr, "error");
+    for error in [
+        "10:12 error[E0203]: Function nothing does not return a value",
+        "14:5 error[E0203]: Function missing must return int",
+        "19:18 error[E0201]: Cannot assign String to int: n",
+        "21:9 error[E0201]: Cannot assign String to int: n",
+        "22:11 error[E0201]: Function twice expects int for argument n, found String",
+        "23:11 error[E0201]: Function greet expects &String for argument name, found String",
+        "24:19 error[E0204]: literal 300 does not fit in i8",
+        "25:8 error[E0201]: Condition must be an integer, found String",
+        "26:15 error[E0201]: Cannot apply + to char and int",
+        "28:13 error[E0202]: Function twice expects 1 arguments, found 2",
+        "29:21 error[E0205]: Only integers can be laid out in format string \"{:x}\"",
+        "30:19 error[E0206]: const limit must be known at compile time",
+        "31:17 error[E0206]: Loop bounds must be known at compile time",
+        "33:13 error[E0206]: Variables cannot hold references: r",
+        "34:21 error[E0201]: Cannot assign int to i8: small",
+        "35:9 error[E0206]: Cannot assign a computed string to s",
+        "36:13 error[E0206]: The mode of open must be a const string",
+        "37:11 error[E0201]: Function print expects a string, char or integer, found &int",
+        "40:54 error[E0206]: Function seven takes 7 arguments, at most 6 are supported",
+        "44:6 error[E0203]: Function falls may end without returning int",
+        "50:6 error[E0206]: Function pointer cannot return a reference",
+        "55:18 error[E0206]: Function read reads into a string variable",
+        "56:14 error[E0206]: Only string variables and literals can be iterated",
+    ] {
+        assert!(errors.iter().any(|e| e == error), "{error}");
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_main_errors() {
+    let stderr = compile_error("no_main");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [" error[E0206]: Executables need a function main"]
+    );
+    assert!(!stderr.contains("E0301"));
+
+    let stderr = compile_error("main_signature");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [
+            "1:11 error[E0202]: Function main takes no arguments, found 1",
+            "1:6 error[E0203]: Function main must return an integer or nothing, found String",
+        ]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_name_errors() {
+    let stderr = compile_error("name_errors");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [
+            "1:20 error[E0103]: Duplicate definition of a",
+            "5:6 error[E0103]: Duplicate definition of helper",
+            "9:11 error[E0104]: count is used before its declaration",
+            "11:9 error[E0103]: Duplicate definition of count",
+            "12:11 error[E0101]: undefined variable: missing",
+            "13:5 error[E0102]: undefined function: undefined_fn",
+            "17:11 error[E0101]: undefined variable: inner",
+            "19:13 error[E0103]: Duplicate definition of i",
+        ]
+    );
+    assert!(stderr.contains(
+        "tests/fixtures/name_errors.jc:11:9
+   |
+10 |     let count = 1
+   |         ----- first defined here
+11 |     let count = 2
+   |         ^^^^^ defined again here
+"
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_parse_error_location() {
+    let stderr = compile_error("parse_error");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        ["2:11 error[E0001]: Expected =, found 5"]
+    );
+    assert!(stderr.contains("2 |     let x 5\n  |           ^\n"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_syntax_errors() {
+    let stderr = compile_error("syntax_errors");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [
+            "2:11 error[E0001]: Expected =, found 5",
+            "4:1 error[E0001]: Expected expression, found }",
+            "7:25 error[E0001]: Expected ), found end of line",
+            "8:16 error[E0001]: Expected expression, found {",
+            "11:11 error[E0001]: Expected in, found 0",
+            "14:18 error[E0001]: Expected end of line, found total",
+            "16:1 error[E0001]: Unmatched }",
+        ]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_mutability_errors() {
+    let stderr = compile_error("mutability_errors");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [
+            "4:5 error[E0106]: Cannot assign to parameter n",
+            "10:5 error[E0106]: Cannot assign to const name",
+            "11:5 error[E0106]: Cannot assign to const LIMIT",
+            "14:9 error[E0106]: Cannot assign to loop variable i",
+        ]
+    );
+    assert!(stderr.contains("   = help: declare LIMIT with let to assign it\n"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_lints() {
+    assert_eq!(
+        compile_warnings("lints", &[]),
+        [
+            "3:6 warning[unused-function]: function helper is never called from main or an exported function",
+            "7:6 warning[unused-function]: function unused_helper is never called from main or an exported function",
+            "16:9 warning[unused-variable]: unused variable: unused",
+            "18:14 warning[empty-range]: loop over the empty range 5..0 never runs",
+            "25:5 warning[unreachable-code]: unreachable statement after return",
+        ]
+    );
+    assert_eq!(compile_src("lints"), "012");
+
+    let flags = ["--", "--allow", "unused-function", "--allow", "empty-range"];
+    assert_eq!(
+        compile_warnings("lints", &flags),
+        [
+            "16:9 warning[unused-variable]: unused variable: unused",
+            "25:5 warning[unreachable-code]: unreachable statement after return",
+        ]
+    );
+
+    let stderr = compile_error_with("lints", &["--", "--deny-warnings"]);
+    assert!(stderr.contains("error: warnings are denied by --deny-warnings"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_error_format_json() {
+    let json_lines = |stderr: String| -> Vec<String> {
+        stderr
+            .lines()
+            .filter(|line| line.starts_with('{'))
+            .map(str::to_owned)
+            .collect()
+    };
+
+    let errors = json_lines(compile_error_with(
+        "name_errors",
+        &["--", "--error-format=json"],
+    ));
+    assert_eq!(errors.len(), 8);
+    assert!(errors[0].ends_with(
+        r#"tests/fixtures/name_errors.jc","line_start":1,"column_start":13,"line_end":1,"column_end":14,"byte_start":12,"byte_end":13,"is_primary":false,"label":"first defined here"}],"help":[],"suggestions":[]}"#
+    ));
+    assert!(errors[0].starts_with(
+        r#"{"severity":"error","code":"E0103","message":"Duplicate definition of a","spans":[{"file":""#
+    ));
+
+    let child = run_compiler("lints", &["--", "--error-format", "json"]);
+    assert!(child.status.success());
+    let stderr = String::from_utf8(child.stderr).unwrap();
+    // Past the lines of cargo, stderr only holds diagnostics
+    let mut compiler_lines = stderr
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("Running"))
+        .skip(1);
+    assert!(compiler_lines.all(|line| line.starts_with('{')), "{stderr}");
+    let warnings = json_lines(stderr);
+    assert_eq!(warnings.len(), 5);
+    assert!(warnings[2].contains(
+        r#""suggestions":[{"message":"if this is intentional, prefix it with an underscore","replacement":"_unused","span":{"#
+    ));
+
+    let errors = json_lines(compile_error_with(
+        "const_overflow",
+        &["--", "--error-format=json"],
+    ));
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with(
+        r#"{"severity":"error","code":"E0207","message":"overflow evaluating 100 * 2 as i8","spans":[{"file":""#
+    ));
+    assert!(errors[0].ends_with(
+        r#"tests/fixtures/const_overflow.jc","line_start":4,"column_start":24,"line_end":4,"column_end":33,"byte_start":60,"byte_end":69,"is_primary":true,"label":"overflows i8"}],"help":[],"suggestions":[]}"#
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_multiline() {
+    assert_eq!(compile_src("multiline"), "7 2\nbig\ntwo\nlines\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_unicode() {
+    assert_eq!(
+        compile_src("unicode"),
+        "Hallo wörld €! 10\nHallo 日本語! 9\n6\nwörld €\n"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_lex_errors() {
+    let stderr = compile_error("lex_errors");
+    assert_eq!(
+        diagnostics(&stderr, "error"),
+        [
+            "2:10 error[E0002]: Invalid character '€'",
+            "3:15 error[E0002]: Invalid character '$'",
+            "4:13 error[E0002]: Invalid escape character: Some('q')",
+            "5:13 error[E0002]: Invalid escape character: Some('q')",
+            "6:13 error[E0002]: Unterminated string literal",
+        ]
+    );
+    // The rest of the input is in the comment, nothing after it is reported
+    assert_eq!(
+        diagnostics(&compile_error("lex_comment"), "error"),
+        ["2:5 error[E0002]: Unterminated block comment"]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_shadowing() {
+    assert_eq!(compile_src("shadowing"), "12201\n");
+}
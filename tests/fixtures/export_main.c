@@ -0,0 +1,12 @@
+#include <stdio.h>
+
+long add(long a, long b);
+long fact(long n);
+long sum_of_squares(long a, long b);
+void greet(void);
+
+int main(void) {
+    greet();
+    printf("%ld %ld %ld\n", add(40, 2), fact(10), sum_of_squares(3, 4));
+    return 0;
+}